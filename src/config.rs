@@ -1,5 +1,9 @@
 use std::env;
 
+use tracing::warn;
+
+use crate::types::CC_PER_CENT;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ExecMode {
     Live,
@@ -15,22 +19,46 @@ impl ExecMode{
         }
     }
 
-    /// Read `EXEC_MODE`` from the environment.
-    pub fn from_env() -> Self {
-        let raw = env::var("EXEC_MODE").unwrap_or_else(|_| "paper".to_string());
-        Self::parse(&raw)
-    }
-
     pub fn is_paper(self) -> bool {
         matches!(self, ExecMode::Paper)
     }
 }
 
+/// PAPER: where cancels at our price level are assumed to come from.
+/// Trades always come from the front of the queue; cancels we can't see.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueCancelRule {
+    /// Split between ahead/behind us in proportion to their size.
+    Proportional,
+    /// Cancels come from behind us first (pessimistic: we don't move up).
+    BackFirst,
+    /// Cancels come from ahead of us first (optimistic).
+    FrontFirst,
+}
+
+impl QueueCancelRule {
+    pub fn parse(raw: &str) -> Self {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "back" | "back_first" => QueueCancelRule::BackFirst,
+            "front" | "front_first" => QueueCancelRule::FrontFirst,
+            _ => QueueCancelRule::Proportional,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Config {
     pub exec_mode: ExecMode,
     // (optional) realism knobs:
     pub paper_reject_postonly_cross: bool,
+    pub paper_queue_cancel_rule: QueueCancelRule,
+    // Simulated exchange latency, uniform in [min, max] ms:
+    // place/amend ack (order isn't live until then), cancel, and fill report.
+    pub paper_ack_delay_ms: (u64, u64),
+    pub paper_cancel_delay_ms: (u64, u64),
+    pub paper_fill_delay_ms: (u64, u64),
+    // Paper account starting cash (cc). Buys beyond available cash are rejected.
+    pub paper_start_cash_cc: i64,
     // How often the engine runs.
     // Even if your WS updates are fast, 20–50ms is usually plenty.
     pub tick_ms: u64,
@@ -82,6 +110,9 @@ pub struct Config {
     pub max_order_qty: u64,            // hard safety cap
     pub catchup_aggressiveness: f64,   // 0.0..1.0 how fast to catch up
     pub catchup_balance_boost: f64,    // multiplier in Balance mode
+    // Worst-case settlement loss one window may carry: cost - min(yes, no) * $1.
+    // Buys that would go past it are sized down (or skipped) unless they reduce it.
+    pub max_window_loss_cc: i64,
 
 
     // Resting order management
@@ -93,6 +124,21 @@ pub struct Config {
     pub maker_qty_price_tol_cents: u8,          // price-vs-qty tolerance when choosing (price,qty) under cap (normal)
     pub maker_qty_price_tol_cents_balance: u8,  // same tolerance in Balance mode
 
+    // GTC quote expiry: every resting quote carries expiration_ts so nothing outlives
+    // its window, even if a cancel is lost. Expiry = close_ts - margin, optionally
+    // shortened to now + max life for the current mode (0 = no extra cap).
+    pub quote_expiry_margin_s: i64,
+    pub quote_max_life_s_accumulate: i64,
+    pub quote_max_life_s_hedge: i64,
+    pub quote_max_life_s_balance: i64,
+
+    // Quote ladders: extra resting rungs below the top quote when the book is thin.
+    // Each rung is (cents below top maker price, qty), e.g. [(1, 2), (3, 4), (6, 8)].
+    // The whole ladder is planned under the pair-cost cap as if every rung filled.
+    pub ladder_rungs: Vec<(u8, u64)>, // empty = single quote per side
+    pub ladder_max_top_depth: i64,    // only ladder when best-bid depth on that side is <= this
+    pub ladder_max_extra_qty: u64,    // cap on qty across the extra rungs
+
     // -------- Inventory-skewed dual quoting knobs --------
     // When imbalance_ratio >= this, we skew quoting:
     // - hedge side becomes "more competitive" (can force top to ask-1 in maker quote)
@@ -129,7 +175,69 @@ pub struct Config {
     /// back to a smaller qty (down to 1) if larger sizes can't be priced under cap.
     pub short_side_min_order_qty: u64,
 
+    // --- Early pair lock-in ---
+    // If best_bid(YES) + best_bid(NO) beats our pair cost by enough, sell matched pairs now
+    // instead of waiting for settlement (same edge, capital freed for the rest of the window).
+    pub lockin_enabled: bool,
+    pub lockin_min_edge_cc: i64,      // required edge per pair after fees (cent-cents)
+    pub lockin_max_pairs: u64,        // cap on pairs exited per lock-in
+    pub lockin_leg_timeout_ms: u64,   // give up on the second leg after this long (hedge logic takes over)
+    pub lockin_max_duration_ms: u64,  // no lock-in lasts longer than this, whatever its state
+
     pub results_file: String,
+
+    // Settlement tracking (settlement.rs): actual outcome per window once the market settles.
+    pub settlements_file: String,
+    pub settlement_poll_ms: u64,
+    pub settlement_max_wait_s: u64, // stop waiting for a market to settle after this long
+
+    // Graceful shutdown: how long we wait for resting-order cancels to be confirmed.
+    pub shutdown_cancel_timeout_ms: u64,
+
+    // Order flags sent with every create_order.
+    pub self_trade_prevention_type: Option<String>, // "taker_at_cross" | "maker" | None = exchange default
+    pub cancel_order_on_pause: bool,                // resting (GTC) quotes only
+
+    // Exchange pauses: poll get_exchange_status and suspend the engine while trading is off.
+    pub exchange_status_poll_ms: u64,
+    pub pause_retry_ms: u64, // after a pause error on an order/WS, try again after this long
+    // Schedule (maintenance windows) and announcements are polled this often.
+    pub exchange_schedule_poll_ms: u64,
+    // Stop trading this long before a scheduled maintenance window starts.
+    pub maintenance_lead_s: i64,
+
+    // Stale-feed breaker (state::feed): no book update on a ticker for this long (or a seq
+    // gap) pulls its quotes until a fresh snapshot arrives.
+    pub feed_stale_ms: u64,
+
+    // Exec latency/outcome summaries (exec::telemetry) are logged this often. 0 = off.
+    pub exec_telemetry_log_s: u64,
+
+    // Pre-trade risk gate (engine::risk): every place/amend must pass these.
+    pub risk_max_order_notional_cc: i64, // price * qty per buy
+    pub risk_price_band_cents: u8,       // buys <= ask + band, sells >= bid - band
+    pub risk_max_orders_per_s: usize,    // places + amends per ticker, rolling 1s
+    pub risk_max_open_orders: usize,     // live orders per ticker
+
+    // Portfolio limits across all tickers (engine::portfolio). Over any of these,
+    // only hedging buys (and sells) go out.
+    pub portfolio_max_cost_cc: i64,
+    pub portfolio_max_unhedged_cc: i64,
+    pub portfolio_max_worst_loss_cc: i64,
+
+    // Loss governor (governor.rs): halts trading until a manual `reset-governor`.
+    pub governor_state_file: String,
+    pub governor_day_start_utc_h: u32, // trading day rolls over at this UTC hour
+    pub governor_max_daily_loss_cc: i64,
+    pub governor_max_drawdown_cc: i64, // from the day's peak PnL
+    pub governor_poll_ms: u64,
+
+    // Balance-aware sizing (sizing.rs): buys never spend more than free capital.
+    pub sizing_enabled: bool,
+    pub balance_poll_ms: u64,
+    pub sizing_reserve_cc: i64,        // always left untouched
+    pub sizing_window_fraction: f64,   // of equity, split across tickers: max cost basis per window
+    pub sizing_reject_backoff_ms: u64, // no new buys on a ticker after an insufficient-balance reject
 }
 
 impl Default for Config {
@@ -137,6 +245,11 @@ impl Default for Config {
         Self {
             exec_mode: ExecMode::Live,
             paper_reject_postonly_cross: true,
+            paper_queue_cancel_rule: QueueCancelRule::Proportional,
+            paper_ack_delay_ms: (30, 80),
+            paper_cancel_delay_ms: (30, 80),
+            paper_fill_delay_ms: (10, 40),
+            paper_start_cash_cc: 1_000 * 100 * CC_PER_CENT, // $1,000
 
             tick_ms: 250,
 
@@ -170,6 +283,7 @@ impl Default for Config {
             max_order_qty: 25,
             catchup_aggressiveness: 0.45,
             catchup_balance_boost: 1.5,
+            max_window_loss_cc: 50 * 100 * CC_PER_CENT, // $50
 
             cancel_stale_ms: 120000,
             min_resting_life_ms: 1000,
@@ -180,6 +294,15 @@ impl Default for Config {
             maker_qty_price_tol_cents: 2,
             maker_qty_price_tol_cents_balance: 1,
 
+            quote_expiry_margin_s: 5,
+            quote_max_life_s_accumulate: 0,
+            quote_max_life_s_hedge: 0,
+            quote_max_life_s_balance: 0,
+
+            ladder_rungs: Vec::new(),
+            ladder_max_top_depth: 50,
+            ladder_max_extra_qty: 20,
+
             // Inventory-skew defaults (tune these!)
             skew_imbalance_start: 0.05,
             cancel_drift_cents_hedge: 1,
@@ -198,7 +321,53 @@ impl Default for Config {
 
             short_side_min_order_qty: 6,
 
+            lockin_enabled: true,
+            lockin_min_edge_cc: 100,      // 1 cent per pair
+            lockin_max_pairs: 25,
+            lockin_leg_timeout_ms: 3000,
+            lockin_max_duration_ms: 15_000,
+
             results_file: "results.csv".to_string(),
+
+            settlements_file: "settlements.csv".to_string(),
+            settlement_poll_ms: 30_000,
+            settlement_max_wait_s: 6 * 3600,
+
+            shutdown_cancel_timeout_ms: 5000,
+
+            self_trade_prevention_type: Some("taker_at_cross".to_string()),
+            cancel_order_on_pause: true,
+
+            exchange_status_poll_ms: 5000,
+            pause_retry_ms: 15_000,
+            exchange_schedule_poll_ms: 60_000,
+            maintenance_lead_s: 120,
+
+            feed_stale_ms: 20_000,
+
+            exec_telemetry_log_s: 60,
+
+            // max_order_qty at max_buy_price_cents: a full-size buy always fits.
+            risk_max_order_notional_cc: 25 * 99 * CC_PER_CENT, // $24.75
+            risk_price_band_cents: 5,
+            risk_max_orders_per_s: 10,
+            risk_max_open_orders: 12,
+
+            portfolio_max_cost_cc: 500 * 100 * CC_PER_CENT,      // $500
+            portfolio_max_unhedged_cc: 50 * 100 * CC_PER_CENT,   // $50
+            portfolio_max_worst_loss_cc: 100 * 100 * CC_PER_CENT, // $100
+
+            governor_state_file: "governor.state".to_string(),
+            governor_day_start_utc_h: 0,
+            governor_max_daily_loss_cc: 100 * 100 * CC_PER_CENT, // $100
+            governor_max_drawdown_cc: 150 * 100 * CC_PER_CENT,   // $150
+            governor_poll_ms: 1000,
+
+            sizing_enabled: true,
+            balance_poll_ms: 2000,
+            sizing_reserve_cc: 5 * 100 * CC_PER_CENT, // $5
+            sizing_window_fraction: 0.25,
+            sizing_reject_backoff_ms: 5000,
         }
     }
 }
@@ -206,12 +375,148 @@ impl Default for Config {
 impl Config {
     /// Load config from environment variables (overrides defaults).
     pub fn from_env() -> Self {
+        Self::from_lookup(|key| env::var(key).ok())
+    }
+
+    /// Load config from any key -> value source (env var names as keys).
+    pub fn from_lookup(get: impl Fn(&str) -> Option<String>) -> Self {
         let mut cfg = Self::default();
-        cfg.exec_mode = ExecMode::from_env();
+        cfg.exec_mode = ExecMode::parse(&get("EXEC_MODE").unwrap_or_else(|| "paper".to_string()));
 
-        if let Ok(v) = env::var("RESULTS_FILE") {
+        if let Some(v) = get("PAPER_QUEUE_CANCEL") {
+            cfg.paper_queue_cancel_rule = QueueCancelRule::parse(&v);
+        }
+        // PAPER_*_DELAY_MS="min-max" or a single value
+        set_ms_range(&get, "PAPER_ACK_DELAY_MS", &mut cfg.paper_ack_delay_ms);
+        set_ms_range(&get, "PAPER_CANCEL_DELAY_MS", &mut cfg.paper_cancel_delay_ms);
+        set_ms_range(&get, "PAPER_FILL_DELAY_MS", &mut cfg.paper_fill_delay_ms);
+        // PAPER_START_CASH in dollars
+        let mut start_cash = cfg.paper_start_cash_cc as f64 / (100.0 * CC_PER_CENT as f64);
+        set_parsed(&get, "PAPER_START_CASH", &mut start_cash);
+        cfg.paper_start_cash_cc = (start_cash * 100.0 * CC_PER_CENT as f64).round() as i64;
+        if let Some(v) = get("RESULTS_FILE") {
             cfg.results_file = v;
         }
+        if let Some(v) = get("SETTLEMENTS_FILE") {
+            cfg.settlements_file = v;
+        }
+        if let Some(v) = get("GOVERNOR_STATE_FILE") {
+            cfg.governor_state_file = v;
+        }
+        // LADDER_RUNGS="1:2,3:4,6:8" (offset_cents:qty per rung)
+        if let Some(v) = get("LADDER_RUNGS") {
+            cfg.ladder_rungs = parse_ladder_rungs(&v);
+        }
+
+        // Strategy knobs (mostly so shadow profiles have something to vary).
+        set_parsed(&get, "TICK_MS", &mut cfg.tick_ms);
+        set_parsed(&get, "MAX_ORDER_QTY", &mut cfg.max_order_qty);
+        set_parsed(&get, "MAX_WINDOW_LOSS_CC", &mut cfg.max_window_loss_cc);
+        set_parsed(&get, "SAFE_PAIR_CC", &mut cfg.safe_pair_cc);
+        set_parsed(&get, "TARGET_PAIR_CC", &mut cfg.target_pair_cc);
+        set_parsed(&get, "BOOTSTRAP_PAIR_CC", &mut cfg.bootstrap_pair_cc);
+        set_parsed(&get, "BALANCE_PAIR_CC", &mut cfg.balance_pair_cc);
+        set_parsed(&get, "MAKER_IMPROVE_TICK", &mut cfg.maker_improve_tick);
+        set_parsed(&get, "MAKER_MAX_EDGE_CENTS", &mut cfg.maker_max_edge_cents);
+        set_parsed(&get, "CANCEL_DRIFT_CENTS", &mut cfg.cancel_drift_cents);
+        set_parsed(&get, "TAKER_COOLDOWN_MS", &mut cfg.taker_cooldown_ms);
+        set_parsed(&get, "MIN_TAKER_IMPROVE_CC", &mut cfg.min_taker_improve_cc);
+        set_parsed(&get, "MAKER_FIRST_MS", &mut cfg.maker_first_ms);
+        set_parsed(&get, "LOCKIN_ENABLED", &mut cfg.lockin_enabled);
+        set_parsed(&get, "LOCKIN_MIN_EDGE_CC", &mut cfg.lockin_min_edge_cc);
+        set_parsed(&get, "PORTFOLIO_MAX_COST_CC", &mut cfg.portfolio_max_cost_cc);
+        set_parsed(&get, "PORTFOLIO_MAX_UNHEDGED_CC", &mut cfg.portfolio_max_unhedged_cc);
+        set_parsed(&get, "PORTFOLIO_MAX_WORST_LOSS_CC", &mut cfg.portfolio_max_worst_loss_cc);
+        set_parsed(&get, "FEED_STALE_MS", &mut cfg.feed_stale_ms);
+        set_parsed(&get, "MAINTENANCE_LEAD_S", &mut cfg.maintenance_lead_s);
+        set_parsed(&get, "SIZING_ENABLED", &mut cfg.sizing_enabled);
+        set_parsed(&get, "SIZING_RESERVE_CC", &mut cfg.sizing_reserve_cc);
+        set_parsed(&get, "SIZING_WINDOW_FRACTION", &mut cfg.sizing_window_fraction);
+        set_parsed(&get, "GOVERNOR_DAY_START_UTC_H", &mut cfg.governor_day_start_utc_h);
+        set_parsed(&get, "GOVERNOR_MAX_DAILY_LOSS_CC", &mut cfg.governor_max_daily_loss_cc);
+        set_parsed(&get, "GOVERNOR_MAX_DRAWDOWN_CC", &mut cfg.governor_max_drawdown_cc);
         cfg
     }
+
+    /// A shadow profile: `SHADOW_<NAME>_<KEY>` overrides `<KEY>`, always paper, trading
+    /// the same series as `base`, with results files labelled by profile unless set.
+    pub fn shadow_from_env(base: &Config, name: &str) -> Self {
+        let prefix = format!("SHADOW_{}_", name.to_ascii_uppercase());
+        let own = |key: &str| env::var(format!("{prefix}{key}")).ok();
+
+        let mut cfg = Self::from_lookup(|key| own(key).or_else(|| env::var(key).ok()));
+        cfg.exec_mode = ExecMode::Paper;
+        cfg.series_tickers = base.series_tickers.clone();
+        if own("RESULTS_FILE").is_none() {
+            cfg.results_file = labelled_path(&base.results_file, name);
+        }
+        if own("SETTLEMENTS_FILE").is_none() {
+            cfg.settlements_file = labelled_path(&base.settlements_file, name);
+        }
+        if own("GOVERNOR_STATE_FILE").is_none() {
+            cfg.governor_state_file = labelled_path(&base.governor_state_file, name);
+        }
+        cfg
+    }
+}
+
+/// Shadow profile names from `SHADOW_PROFILES="wide,tight"`.
+pub fn shadow_profile_names() -> Vec<String> {
+    env::var("SHADOW_PROFILES")
+        .unwrap_or_default()
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Parse `key` into `dst` if it's set. A value that doesn't parse keeps the default, loudly:
+/// a risk limit silently left at its default is worse than a startup warning.
+fn set_parsed<T: std::str::FromStr>(get: &impl Fn(&str) -> Option<String>, key: &str, dst: &mut T) {
+    let Some(raw) = get(key) else { return; };
+    match raw.trim().parse() {
+        Ok(v) => *dst = v,
+        Err(_) => warn_unparsed(key, &raw),
+    }
+}
+
+fn set_ms_range(get: &impl Fn(&str) -> Option<String>, key: &str, dst: &mut (u64, u64)) {
+    let Some(raw) = get(key) else { return; };
+    match parse_ms_range(&raw) {
+        Some(v) => *dst = v,
+        None => warn_unparsed(key, &raw),
+    }
+}
+
+fn warn_unparsed(key: &str, raw: &str) {
+    warn!(key, raw, "config: can't parse value; keeping the default");
+}
+
+/// "results.csv" + "wide" -> "results.wide.csv"
+fn labelled_path(path: &str, label: &str) -> String {
+    match path.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() && !ext.contains('/') => format!("{stem}.{label}.{ext}"),
+        _ => format!("{path}.{label}"),
+    }
+}
+
+fn parse_ms_range(raw: &str) -> Option<(u64, u64)> {
+    let (lo, hi): (u64, u64) = match raw.split_once('-') {
+        Some((a, b)) => (a.trim().parse().ok()?, b.trim().parse().ok()?),
+        None => {
+            let v = raw.trim().parse().ok()?;
+            (v, v)
+        }
+    };
+    Some((lo.min(hi), lo.max(hi)))
+}
+
+fn parse_ladder_rungs(raw: &str) -> Vec<(u8, u64)> {
+    raw.split(',')
+        .filter_map(|part| {
+            let (offset, qty) = part.trim().split_once(':')?;
+            Some((offset.trim().parse().ok()?, qty.trim().parse().ok()?))
+        })
+        .filter(|&(_, qty)| qty > 0)
+        .collect()
 }
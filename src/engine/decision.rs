@@ -1,11 +1,17 @@
+use std::ops::RangeInclusive;
 use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 use tracing::{debug, warn};
 
 use crate::config::Config;
+use crate::fees::Liquidity;
 use crate::state::orders::{OrderRec, OrderStatus};
+use crate::state::position::Position;
 use crate::state::ticker::{Market, Mode};
-use crate::types::{ExecCommand, RestingHint, Side, Tif, CC_PER_CENT};
+use crate::types::{Action, ExecCommand, PlaceParams, Side, Tif, CC_PER_CENT};
+
+use super::order_manager::{DesiredState, PairCap, QuoteIntent, QuoteTarget, TakerIntent};
+use super::risk::max_qty_at;
 
 const DOLLAR_CC: i64 = 100 * CC_PER_CENT; // 10000
 
@@ -35,49 +41,63 @@ fn passive_strong_price(cfg: &Config, m: &Market, side: Side) -> Option<u8> {
     let max_price = top.saturating_sub(cfg.dual_strong_backoff_cents);
 
     // Search downwards (we allow going deep; if want to constrain, use min bound)
-    best_price_under_pair_cap(m, side, max_price, 1, cap_cc, false)
+    best_price_under_pair_cap(cfg, m, side, max_price, 1, cap_cc, false)
 }
 
-fn stage_place_order(
-    ticker: &str,
-    m: &mut Market,
-    now: Instant,
-    side: Side,
-    price_cents: u8,
-    qty: u64,
-    tif: Tif,
-    post_only: bool,
-) -> (uuid::Uuid, ExecCommand) {
-    let client_order_id = uuid::Uuid::new_v4();
+/// Record `p` as pending and wrap it for exec. The engine-owned fields are set here:
+/// the GTC expiry, `reduce_only` on sells, and `buy_max_cost_cc` only on buys.
+pub(super) fn stage_place_order(cfg: &Config, m: &mut Market, mut p: PlaceParams) -> ExecCommand {
+    p.expiration_ts = match p.tif {
+        Tif::Gtc => quote_expiration_ts(cfg, m, unix_now_s()),
+        Tif::Ioc => None,
+    };
+    // Sells only ever unwind inventory we hold.
+    p.reduce_only = p.action == Action::Sell;
+    p.buy_max_cost_cc = p.buy_max_cost_cc.filter(|_| p.action == Action::Buy);
 
     m.orders.insert_pending(OrderRec {
-        ticker: ticker.to_string(),
-        side,
-        price_cents,
-        qty,
-        tif,
-        post_only,
+        ticker: p.ticker.clone(),
+        side: p.side,
+        action: p.action,
+        price_cents: p.price_cents,
+        qty: p.qty,
+        tif: p.tif,
+        post_only: p.post_only,
         order_id: None,
-        client_order_id,
+        client_order_id: p.client_order_id,
         status: OrderStatus::PendingAck,
-        created_at: now,
+        created_at: p.decided_at,
+        expiration_ts: p.expiration_ts,
         filled_qty: 0,
     });
 
-    let cmd = ExecCommand::PlaceOrder {
-        ticker: ticker.to_string(),
-        side,
-        price_cents,
-        qty,
-        tif,
-        post_only,
-        client_order_id,
-    };
+    ExecCommand::Place(p)
+}
 
-    (client_order_id, cmd)
+/// True if `after` keeps the window's worst-case settlement loss within `max_window_loss_cc`,
+/// or at least no worse than `before` (hedging out of an over-limit position stays allowed).
+fn within_loss_bound(cfg: &Config, before: &Position, after: &Position) -> bool {
+    let wc = after.worst_case_pnl_cc();
+    wc >= -cfg.max_window_loss_cc || wc >= before.worst_case_pnl_cc()
 }
 
+/// Largest qty <= `qty` buyable on `side` at `price_cents` within the loss bound (0 if none).
+fn loss_bounded_qty(cfg: &Config, pos: &Position, side: Side, price_cents: u8, qty: u64, liq: Liquidity) -> u64 {
+    (0..=qty)
+        .rev()
+        .find(|&q| within_loss_bound(cfg, pos, &pos.simulate_buy(side, price_cents, q as i64, liq)))
+        .unwrap_or(0)
+}
+
+/// Catch-up qty, cut to what the loss bound allows if it all filled at the worst price a
+/// sweep of the asks would reach (capped at `max_buy_price_cents`). 0 = no buy on this side.
 fn desired_buy_qty(cfg: &Config, m: &Market, side: Side, t_rem: i64, window_s: i64) -> u64 {
+    let q = catchup_buy_qty(cfg, m, side, t_rem, window_s);
+    let worst_price = m.book.buy_vwap(side, q).map_or(cfg.max_buy_price_cents, |(w, _)| w.min(cfg.max_buy_price_cents));
+    loss_bounded_qty(cfg, &m.pos, side, worst_price, q, Liquidity::Taker)
+}
+
+fn catchup_buy_qty(cfg: &Config, m: &Market, side: Side, t_rem: i64, window_s: i64) -> u64 {
     let yes = m.pos.yes_qty.max(0) as i64;
     let no  = m.pos.no_qty.max(0) as i64;
 
@@ -130,13 +150,34 @@ fn desired_buy_qty(cfg: &Config, m: &Market, side: Side, t_rem: i64, window_s: i
 fn best_maker_pc_for_side(cfg: &Config, m: &Market, side: Side, cap_cc: i64) -> Option<(u8, i64)> {
     let top = top_maker_price(cfg, m, side)?;
     let min_price = top.saturating_sub(cfg.maker_max_edge_cents);
-    let p = best_price_under_pair_cap(m, side, top, min_price, cap_cc, true)?;
-    let sim = m.pos.simulate_buy(side, p, 1);
+    let p = best_price_under_pair_cap(cfg, m, side, top, min_price, cap_cc, true)?;
+    let sim = m.pos.simulate_buy(side, p, 1, Liquidity::Maker);
     let pc = sim.pair_cost_cc()?;
     Some((p, pc))
 }
 
-fn unix_now_s() -> i64 {
+/// When a GTC quote placed now should expire: `quote_expiry_margin_s` before close,
+/// or sooner if the current mode caps quote lifetime. None if we don't know close_ts.
+fn quote_expiration_ts(cfg: &Config, m: &Market, now_s: i64) -> Option<i64> {
+    let close_exp = m.close_ts?.saturating_sub(cfg.quote_expiry_margin_s.max(0));
+    let max_life_s = match m.mode {
+        Mode::Accumulate => cfg.quote_max_life_s_accumulate,
+        Mode::Hedge => cfg.quote_max_life_s_hedge,
+        Mode::Balance => cfg.quote_max_life_s_balance,
+    };
+    if max_life_s > 0 {
+        Some(close_exp.min(now_s + max_life_s))
+    } else {
+        Some(close_exp)
+    }
+}
+
+/// Too close to the end of the window to place a quote that would outlive its expiry.
+fn quotes_closed(cfg: &Config, m: &Market, now_s: i64) -> bool {
+    quote_expiration_ts(cfg, m, now_s).is_some_and(|exp| exp <= now_s)
+}
+
+pub fn unix_now_s() -> i64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
@@ -173,7 +214,12 @@ fn max_missing_price_cents(cfg: &Config, m: &Market, existing_avg_cc: i64) -> u8
     if max_cc <= 0 {
         return 0;
     }
-    (max_cc / CC_PER_CENT).clamp(0, cfg.max_buy_price_cents as i64) as u8
+    // This is a taker price: leave room for the fee on top of it.
+    let mut p = (max_cc / CC_PER_CENT).clamp(0, cfg.max_buy_price_cents as i64) as u8;
+    while p > 0 && p as i64 * CC_PER_CENT + m.pos.fee_model.taker_fee_cc(p, 1) > max_cc {
+        p -= 1;
+    }
+    p
 }
 
 fn can_rescue_existing(cfg: &Config, m: &Market, existing: Side) -> Option<(u8, i64)> {
@@ -185,7 +231,7 @@ fn can_rescue_existing(cfg: &Config, m: &Market, existing: Side) -> Option<(u8,
         return None; // would not reduce avg
     }
 
-    let sim = m.pos.simulate_buy(existing, p, 1);
+    let sim = m.pos.simulate_buy(existing, p, 1, Liquidity::Maker);
     let new_avg_cc = match existing {
         Side::Yes => sim.avg_yes_cc()?,
         Side::No => sim.avg_no_cc()?,
@@ -347,28 +393,27 @@ fn top_maker_price(cfg: &Config, m: &Market, side: Side) -> Option<u8> {
     Some(p)
 }
 
+/// Highest price in `prices` where buying `qty` on top of `pos` keeps
+/// pair cost under `cap_cc` (and, if `noworse_than` is set, not above it) and stays within
+/// the window loss bound.
 fn best_price_under_pair_cap_qty(
-    m: &Market,
+    cfg: &Config,
+    pos: &Position,
     side: Side,
-    max_price: u8,
-    min_price: u8,
+    prices: RangeInclusive<u8>,
     cap_cc: i64,
-    require_noworse: bool,
+    noworse_than: Option<i64>,
     qty: i64,
 ) -> Option<u8> {
-    let old_pair = m.pos.pair_cost_cc();
-    if min_price > max_price { return None; }
-
-    for p in (min_price..=max_price).rev() {
-        let sim = m.pos.simulate_buy(side, p, qty);
+    for p in prices.rev() {
+        let sim = pos.simulate_buy(side, p, qty, Liquidity::Maker);
+        if !within_loss_bound(cfg, pos, &sim) { continue; }
         let Some(new_pc) = sim.pair_cost_cc() else { continue; };
 
         if new_pc > cap_cc { continue; }
 
-        if require_noworse {
-            if let Some(old_pc) = old_pair {
-                if new_pc > old_pc { continue; }
-            }
+        if noworse_than.is_some_and(|old_pc| new_pc > old_pc) {
+            continue;
         }
 
         return Some(p);
@@ -376,6 +421,8 @@ fn best_price_under_pair_cap_qty(
     None
 }
 
+/// Plan the (price, qty) ladder for one side: rung 0 is the top quote, deeper rungs
+/// come from `cfg.ladder_rungs` when the book is thin. Empty if nothing fits the cap.
 fn best_maker_price_and_qty_under_cap(
     cfg: &Config,
     m: &Market,
@@ -385,7 +432,8 @@ fn best_maker_price_and_qty_under_cap(
     cap_cc: i64,
     require_noworse: bool,
     desired_qty: u64,
-) -> Option<(u8, u64)> {
+) -> Vec<(u8, u64)> {
+    let noworse_than = if require_noworse { m.pos.pair_cost_cc() } else { None };
     let desired_qty = desired_qty
         .max(1)
         .min(cfg.max_order_qty.max(1));
@@ -421,19 +469,22 @@ fn best_maker_price_and_qty_under_cap(
     let mut candidates: Vec<(u8, u64)> = Vec::new();
     for q in 1..=desired_qty {
         if let Some(p) = best_price_under_pair_cap_qty(
-            m,
+            cfg,
+            &m.pos,
             side,
-            top,
-            min_price,
+            min_price..=top,
             cap_cc,
-            require_noworse,
+            noworse_than,
             q as i64,
-        ) {
+        ) && q <= max_qty_at(cfg, p)
+        {
             candidates.push((p, q));
         }
     }
 
-    let (best_p, _) = *candidates.iter().max_by_key(|(p, _q)| *p)?;
+    let Some(&(best_p, _)) = candidates.iter().max_by_key(|(p, _q)| *p) else {
+        return Vec::new();
+    };
 
     // Keep only candidates within tol of best_p
     let mut best: Option<(u8, u64)> = None;
@@ -452,13 +503,44 @@ fn best_maker_price_and_qty_under_cap(
         }
     }
 
-    best
+    let Some(first) = best else { return Vec::new(); };
+    let mut ladder = vec![first];
+
+    let thin = m.book.depth_within(side, 0) <= cfg.ladder_max_top_depth;
+    if cfg.ladder_rungs.is_empty() || !thin {
+        return ladder;
+    }
+
+    // Deeper rungs are priced as if every rung above them filled, so the whole
+    // ladder filling still lands under the cap.
+    let mut sim = m.pos.simulate_buy(side, first.0, first.1 as i64, Liquidity::Maker);
+    let mut budget = cfg.ladder_max_extra_qty;
+
+    for &(offset, rung_qty) in &cfg.ladder_rungs {
+        let above = ladder.last().map(|&(p, _)| p).unwrap_or(top);
+        let max_price = top.saturating_sub(offset).min(above.saturating_sub(1));
+        // Sized at the rung's highest price, so any price the search lands on fits the gate.
+        let qty = rung_qty.min(budget).min(max_qty_at(cfg, max_price));
+        if qty == 0 || max_price == 0 {
+            break;
+        }
+
+        let Some(p) = best_price_under_pair_cap_qty(cfg, &sim, side, 1..=max_price, cap_cc, noworse_than, qty as i64) else {
+            break;
+        };
+        sim = sim.simulate_buy(side, p, qty as i64, Liquidity::Maker);
+        budget -= qty;
+        ladder.push((p, qty));
+    }
+
+    ladder
 }
 
 
 /// Search downward for a price that satisfies pair-cost constraints.
 /// cap_cc is in cent-cents.
 fn best_price_under_pair_cap(
+    cfg: &Config,
     m: &Market,
     side: Side,
     max_price: u8,
@@ -471,7 +553,8 @@ fn best_price_under_pair_cap(
     if min_price > max_price { return None; }
 
     for p in (min_price..=max_price).rev() {
-        let sim = m.pos.simulate_buy(side, p, 1);
+        let sim = m.pos.simulate_buy(side, p, 1, Liquidity::Maker);
+        if !within_loss_bound(cfg, &m.pos, &sim) { continue; }
         let Some(new_pc) = sim.pair_cost_cc() else {
             // If we don’t have both sides yet, pair_cost is undefined.
             continue;
@@ -491,68 +574,6 @@ fn best_price_under_pair_cap(
     None
 }
 
-/// If we have a resting hint and it’s too old, cancel it.
-/// We do NOT cancel constantly; this is only for “stale” orders.
-fn cancel_stale_if_needed(cfg: &Config, ticker: &str, m: &mut Market, now: Instant) -> Option<ExecCommand> {
-    for side in Side::ALL {
-        let Some(h) = m.resting_hint(side).as_ref().cloned() else { continue; };
-        let Some(order_id) = h.order_id.clone() else { continue; };
-
-        let age_ms = now.duration_since(h.created_at).as_millis() as u64;
-        if age_ms < cfg.min_resting_life_ms { continue; }
-
-        // If we already requested cancel, don’t spam cancel every tick.
-        if let Some(t0) = h.cancel_requested_at {
-            let since = now.duration_since(t0).as_millis() as u64;
-            if since < cfg.cancel_retry_ms { continue; }
-        }
-
-        if age_ms >= cfg.cancel_stale_ms {
-            // Mark cancel requested in hint.
-            if let Some(hm) = m.resting_hint_mut(side).as_mut() {
-                hm.cancel_requested_at = Some(now);
-            }
-            return Some(ExecCommand::CancelOrder {
-                ticker: ticker.to_string(),
-                order_id,
-            });
-        }
-    }
-    None
-}
-
-fn cancel_side_if_allowed(
-    cfg: &Config,
-    ticker: &str,
-    m: &mut Market,
-    now: Instant,
-    side: Side,
-) -> Option<ExecCommand> {
-    let Some(h) = m.resting_hint(side).as_ref().cloned() else { return None; };
-    let Some(order_id) = h.order_id.clone() else { return None; };
-
-    let age_ms = now.duration_since(h.created_at).as_millis() as u64;
-    if age_ms < cfg.min_resting_life_ms {
-        return None;
-    }
-
-    if let Some(t0) = h.cancel_requested_at {
-        let since = now.duration_since(t0).as_millis() as u64;
-        if since < cfg.cancel_retry_ms {
-            return None;
-        }
-    }
-
-    if let Some(hm) = m.resting_hint_mut(side).as_mut() {
-        hm.cancel_requested_at = Some(now);
-    }
-
-    Some(ExecCommand::CancelOrder { 
-        ticker: ticker.to_string(), 
-        order_id,
-    })
-}
-
 /// Signal-free working side selection, but "pair-cost smart".
 /// Chooses the side that would most reduce avg_yes+avg_no if a 1-lot maker fill happens.
 ///
@@ -599,7 +620,7 @@ fn choose_working_side_simple(cfg: &Config, m: &Market, t_rem: i64) -> Side {
 
     for side in Side::ALL {
         // Respect imbalance cap (don't choose side that would push beyond allowed imbalance)
-        let would = m.pos.simulate_buy(side, 0, 1);
+        let would = m.pos.simulate_buy(side, 0, 1, Liquidity::Maker);
         if would.imbalance_ratio() > imbalance_cap {
             continue;
         }
@@ -611,12 +632,12 @@ fn choose_working_side_simple(cfg: &Config, m: &Market, t_rem: i64) -> Side {
         let min_price = top.saturating_sub(cfg.maker_max_edge_cents);
 
         // Find a price in [min_price..top] that keeps us under cap_cc and (otionally doesn't worsen)
-        let Some(p) = best_price_under_pair_cap(m, side, top, min_price, cap_cc, true) else {
+        let Some(p) = best_price_under_pair_cap(cfg, m, side, top, min_price, cap_cc, true) else {
             continue;
         };
 
         // Simulate a 1-lot fill at p and evaluate new pair cost
-        let sim = m.pos.simulate_buy(side, p, 1);
+        let sim = m.pos.simulate_buy(side, p, 1, Liquidity::Maker);
         let Some(new_pc) = sim.pair_cost_cc() else {
             continue;
         };
@@ -659,13 +680,12 @@ fn hedge_side(m: &Market) -> Side {
 /// place an IOC buy at the ask (limit at ask, post_only=false).
 fn maybe_opportunistic_taker(
     cfg: &Config,
-    ticker: &str,
     m: &mut Market,
     now: Instant,
     t_rem: i64,
     window_s: i64,
     desired_side: Side,
-) -> Option<ExecCommand> {
+) -> Option<TakerIntent> {
     // ---------------BOOTSTRAP TAKER-----------------
     // Until we have both sides, only taker in tight spreads
     // or when we're forcing balance late window.
@@ -701,29 +721,30 @@ fn maybe_opportunistic_taker(
             }
 
             // If not Balance mode, require a tight spread to cross
-            if m.mode != Mode::Balance {
-                let best = m.book.best_bid(side)?;
-                if ask > best.saturating_add(cfg.aggressive_tick) {
-                    return None;
-                }
+            if m.mode != Mode::Balance && m.book.spread(side)? > cfg.aggressive_tick {
+                return None;
             }
         } else {
             // Flat: only take if tight spread (otherwise maker quote)
-            if m.mode != Mode::Balance {
-                let best = m.book.best_bid(side)?;
-                if ask > best.saturating_add(cfg.aggressive_tick) {
-                    return None;
-                }
+            if m.mode != Mode::Balance && m.book.spread(side)? > cfg.aggressive_tick {
+                return None;
             }
         }
 
-        let qty = desired_buy_qty(cfg, m, side, t_rem, window_s);
-        let (_client_order_id, cmd) = stage_place_order(
-            ticker, m, now, side, ask, qty, Tif::Ioc, false
-        );
+        let qty = desired_buy_qty(cfg, m, side, t_rem, window_s).min(max_qty_at(cfg, ask));
+        if qty == 0 {
+            return None;
+        }
+        // Missing side: spend up to the bootstrap cap. Flat: no pair yet, so hold the ask.
+        let max_price = if m.pos.yes_qty > 0 || m.pos.no_qty > 0 {
+            avg_cc_for(m, side.other()).map_or(ask, |avg| max_missing_price_cents(cfg, m, avg))
+        } else {
+            ask
+        };
+        let max_cost_cc = max_price.max(ask) as i64 * CC_PER_CENT * qty as i64;
 
         set_last_taker(m, side, now);
-        return Some(cmd);
+        return Some(TakerIntent::new(side, Action::Buy, ask, qty).with_max_cost_cc(max_cost_cc));
     }
 
     let total = total_qty(m);
@@ -743,16 +764,17 @@ fn maybe_opportunistic_taker(
         old_pc
     };
 
-    let mut best: Option<(Side, u8, i64, u64)> = None;
+    // (side, ask, new pair cost, qty, pair-cost cap the buy was allowed under)
+    let mut best: Option<(Side, u8, i64, u64, i64)> = None;
 
     for side in Side::ALL {
         // If we already have a maker resting on this side, give it time before paying taker fees.
         if !desperate {
-            if let Some(h) = m.resting_hint(side).as_ref() {
-                let age_ms = now.duration_since(h.created_at).as_millis() as u64;
-                if age_ms < cfg.maker_first_ms {
-                    continue;
-                }
+            let maker_too_young = m.working(side)
+                .iter()
+                .any(|w| (now.duration_since(w.created_at).as_millis() as u64) < cfg.maker_first_ms);
+            if maker_too_young {
+                continue;
             }
         }
 
@@ -769,16 +791,23 @@ fn maybe_opportunistic_taker(
             }
         }
 
-        let qty = desired_buy_qty(cfg, m, side, t_rem, window_s);
+        let qty = desired_buy_qty(cfg, m, side, t_rem, window_s).min(max_qty_at(cfg, ask));
 
         if !must_balance {
-            let would = m.pos.simulate_buy(side, 0, qty as i64); // price doesn't matter for imbalance_ratio
+            let would = m.pos.simulate_buy(side, 0, qty as i64, Liquidity::Maker); // price doesn't matter for imbalance_ratio
             if would.imbalance_ratio() > imbalance_cap {
                 continue;
             }
         }
 
-        let sim = m.pos.simulate_buy(side, ask, qty as i64);
+        if qty == 0 {
+            continue; // loss bound leaves nothing to buy here
+        }
+
+        let sim = m.pos.simulate_buy(side, ask, qty as i64, Liquidity::Taker);
+        if !within_loss_bound(cfg, &m.pos, &sim) {
+            continue;
+        }
         let Some(new_pc) = sim.pair_cost_cc() else {
             continue;
         };
@@ -802,8 +831,8 @@ fn maybe_opportunistic_taker(
             if new_pc <= cap_when_balancing {
                 // keep your "pick best" logic
                 match best {
-                    None => best = Some((side, ask, new_pc, qty)),
-                    Some((_, _, bp, _)) if new_pc < bp => best = Some((side, ask, new_pc, qty)),
+                    None => best = Some((side, ask, new_pc, qty, cap_when_balancing)),
+                    Some((_, _, bp, _, _)) if new_pc < bp => best = Some((side, ask, new_pc, qty, cap_when_balancing)),
                     _ => {}
                 }
             }
@@ -824,10 +853,11 @@ fn maybe_opportunistic_taker(
         }
 
         // Tight-spread crossings: only when it's a BIG improvement (worth fee)
-        if tight {
+        let cap = if tight {
             if improve < cfg.taker_big_improve_cc {
                 continue;
             }
+            old_pc - cfg.taker_big_improve_cc
         } else {
             // Wide spread: require meaningful improvement AND taker must be at least as good as maker
             if improve < cfg.min_taker_improve_cc {
@@ -836,37 +866,41 @@ fn maybe_opportunistic_taker(
             if new_pc > maker_pc {
                 continue; // maker is better, so don't pay taker fee
             }
-        }
+            (old_pc - cfg.min_taker_improve_cc).min(maker_pc)
+        };
 
         // If we get here, taker is allowed
         match best {
-            None => best = Some((side, ask, new_pc, qty)),
-            Some((_, _, bp, _)) if new_pc < bp => best = Some((side, ask, new_pc, qty)),
+            None => best = Some((side, ask, new_pc, qty, cap)),
+            Some((_, _, bp, _, _)) if new_pc < bp => best = Some((side, ask, new_pc, qty, cap)),
             _ => {}
         }
     }
 
-    let (side, ask, _new_pc, qty) = best?;
+    let (side, ask, _new_pc, qty, cap) = best?;
 
-    let (_client_order_id, cmd) = stage_place_order(
-        ticker, m, now, side, ask, qty, Tif::Ioc, false
-    );
-    set_last_taker(m, side, now);
-    return Some(cmd);
+    // Let the exchange enforce the budget: whatever fills must keep pair cost within `cap`.
+    // The budget is fee-inclusive; the exchange limit is on contract cost only.
+    let at_ask_cc = ask as i64 * CC_PER_CENT * qty as i64;
+    let fee_cc = m.pos.fee_model.taker_fee_cc(ask, qty);
+    let max_cost_cc = m.pos
+        .max_buy_cost_cc(side, qty as i64, cap)
+        .map_or(at_ask_cc, |b| (b - fee_cc).max(at_ask_cc));
 
+    set_last_taker(m, side, now);
+    Some(TakerIntent::new(side, Action::Buy, ask, qty).with_max_cost_cc(max_cost_cc))
 }
 
 /// Maker quote logic:
-/// Maintain at most one resting order, don’t churn, and keep prices within pair-cost constraints.
+/// Target price/size for each resting rung on this side, within pair-cost constraints.
+/// Whether the current quotes are close enough is the order manager's call.
 fn maybe_maker_quote(
     cfg: &Config,
-    ticker: &str,
-    m: &mut Market,
-    now: Instant,
+    m: &Market,
     t_rem: i64,
     window_s: i64,
     desired_side: Side,
-) -> Option<ExecCommand> {
+) -> Option<Vec<QuoteTarget>> {
 
     let cap_target = cfg.target_pair_cc;
     let cap_safe = cfg.safe_pair_cc;
@@ -891,7 +925,7 @@ fn maybe_maker_quote(
     if !has_pair(m) {
         // Flat: just quote near top maker price on desired_side.
         if m.pos.yes_qty == 0 && m.pos.no_qty == 0 {
-            return place_or_manage_resting(cfg, ticker, m, now, desired_side, top, 1, false);
+            return Some(vec![simple_target(cfg, m, desired_side, top, 1, false)]);
         }
 
         // one-sided bootstrap:
@@ -911,21 +945,23 @@ fn maybe_maker_quote(
             }
             // Allow "deep" quotes in bootstrap (do NOT enforce maker_max_edge here)
             let p = top.min(max_missing);
-            return place_or_manage_resting(cfg, ticker, m,  now, desired_side, p, 1, false);
+            return Some(vec![simple_target(cfg, m, desired_side, p, 1, false)]);
         } else {
             // Rescue-buy side: only if it improves avg and we haven't exceeded max one sided qty
             if qty_for(m, existing) >= cfg.bootstrap_max_one_side_qty {
                 return None;
             }
             let (p, _improve) = can_rescue_existing(cfg, m, existing)?;
-            return place_or_manage_resting(cfg, ticker, m, now, desired_side, p, 1, false);
+            return Some(vec![simple_target(cfg, m, desired_side, p, 1, false)]);
         }
     }
 
     // --------------NORMAL (PAIRED) MAKER QUOTE--------
     // 1) Decide desired qty first (so price selection is qty-aware)
-    let mut qty: u64 = desired_buy_qty(cfg, m, desired_side, t_rem, window_s);
-    if qty == 0 { qty = 1; } // defensive
+    let qty: u64 = desired_buy_qty(cfg, m, desired_side, t_rem, window_s);
+    if qty == 0 {
+        return None; // over the window loss bound at any size
+    }
 
     let old_pc = m.pos.pair_cost_cc().unwrap_or(cap_safe);
 
@@ -1007,7 +1043,7 @@ fn maybe_maker_quote(
 //     let p = p_opt?;
     let desired_qty = qty;
 
-    let mut quote = best_maker_price_and_qty_under_cap(
+    let ladder = best_maker_price_and_qty_under_cap(
         cfg,
         m,
         desired_side,
@@ -1035,220 +1071,58 @@ fn maybe_maker_quote(
     //         "maker_quote: no feasible (price,qty) found under cap in band"
     //     );
     // }
-    let (p, qty) = quote?;
-   
-    // 4) If we already have a resting order on this side, manage it.
-    if let Some(existing) = m.resting_hint(desired_side).as_ref().cloned() {
-        // Look up existing order qty from Orders (no need to store qty in RestingHint)
-        let existing_remaining = m.orders.by_client
-            .get(&existing.client_order_id)
-            .map(|r| r.qty.saturating_sub(r.filled_qty))
-            .unwrap_or(1);
-
-        // Only force a resize if we want MORE (avoids churn when gap shrinks)
-        let want_upsize = qty > existing_remaining;
-
-        // If price is identical and we don't need to upsize, leave it alone.
-        if existing.price_cents == p && !want_upsize {
-            return None;
-        }
-
-        let age_ms = now.duration_since(existing.created_at).as_millis() as u64;
-        if age_ms < cfg.min_resting_life_ms {
-            return None;
-        }
-
-        if let Some(t0) = existing.cancel_requested_at {
-            let since = now.duration_since(t0).as_millis() as u64;
-            if since < cfg.cancel_retry_ms {
-                return None;
-            }
-        }
-
-        let drift = existing.price_cents.abs_diff(p);
-        let drift_threshold = drift_threshold_cents(cfg, m, desired_side);
-
-        // For BUY orders: higher price = more aggressive
-        let more_aggressive = p > existing.price_cents;
-
-        // Decide where you want sticky-down active.
-        // A good default: onl do sticky-down on the hedge (short) side.
-        let hedge = hedge_side(m);
-        let sticky_down = (m.pos.yes_cost_cc != m.pos.no_qty) && desired_side == hedge;
-
-        // Safety check: if we kept the existing order and it filled,
-        // would it violate our CURRENT capp_cc/required_noworse rules?
-        let existing_ok_under_cap = {
-            let sim = m.pos.simulate_buy(desired_side, existing.price_cents, existing_remaining as i64);
-            if let Some(pc) = sim.pair_cost_cc() {
-                if pc > cap_cc {
-                    false 
-                } else if require_noworse {
-                    // require_noworse means "don't worsen relative to current"
-                    match m.pos.pair_cost_cc() {
-                        Some(old_pc) => pc <= old_pc,
-                        None => true,
-                    }
-                } else {
-                    true
-                }
-            } else {
-                true
-            }
-        };
-        let should_cancel = 
-            want_upsize
-            // If sticky-down is OFF, keep current behavior
-            || (!sticky_down && drift >= drift_threshold)
-            // If sticky-down is ON, only cancel for drift when movie UP (more aggressive)
-            || (sticky_down && more_aggressive && drift >= drift_threshold)
-            // Even with sticky-down, cancel if teh existing order is no Longer acceptable
-            || (sticky_down && !existing_ok_under_cap);
-
-        if should_cancel {
-            let Some(order_id) = existing.order_id.clone() else { return None; };
-            if let Some(hm) = m.resting_hint_mut(desired_side).as_mut() {
-                hm.cancel_requested_at = Some(now);
-            }
-            return Some(ExecCommand::CancelOrder {
-                ticker: ticker.to_string(),
-                order_id,
-            });
-        }
-        // tracing::debug!(
-        //     ticker = %ticker,
-        //     mode = ?m.mode,
-        //     yes_qty = m.pos.yes_qty,
-        //     no_qty = m.pos.no_qty,
-        //     imbalance = m.pos.imbalance_ratio(),
-        //     old_pair_cc = ?m.pos.pair_cost_cc(),
-        //     best_bid_yes = ?m.book.best_bid(Side::Yes),
-        //     best_bid_no  = ?m.book.best_bid(Side::No),
-        //     implied_ask_yes = ?m.book.implied_ask(Side::Yes),
-        //     implied_ask_no  = ?m.book.implied_ask(Side::No),
-        //     resting_yes = ?m.resting_hint(Side::Yes).as_ref().map(|h| (h.price_cents, h.order_id.is_some())),
-        //     resting_no  = ?m.resting_hint(Side::No).as_ref().map(|h| (h.price_cents, h.order_id.is_some())),
-        //     "decide: no action (no cancel, no taker, no maker)"
-        // );
+    if ladder.is_empty() {
         return None;
     }
 
-    // 5) Place new resting order with qty
-    let (client_order_id, cmd) = stage_place_order(
-        ticker, m, now, desired_side, p, qty, Tif::Gtc, true
-    );
-
-    let queue_ahead = match desired_side {
-        Side::Yes => m.book.yes_bids[p as usize],
-        Side::No  => m.book.no_bids[p as usize],
-    };
-
-    *m.resting_hint_mut(desired_side) = Some(RestingHint {
-        side: desired_side,
-        price_cents: p,
-        created_at: now,
-        cancel_requested_at: None,
-        client_order_id,
-        order_id: None,
-        // Paper trading
-        queue_ahead,
-    });
-
-    Some(cmd)
+    // 4) Decide where you want sticky-down active.
+    // A good default: only do sticky-down on the hedge (short) side.
+    let hedge = hedge_side(m);
+    let sticky_down = (m.pos.yes_cost_cc != m.pos.no_qty) && desired_side == hedge;
+    let drift_cents = drift_threshold_cents(cfg, m, desired_side);
+
+    // Even with sticky-down, the order manager replaces an existing order once it
+    // would no longer satisfy these cap rules if it filled.
+    let targets = ladder
+        .into_iter()
+        .map(|(p, qty)| QuoteTarget {
+            price_cents: p,
+            qty,
+            drift_cents,
+            sticky_down,
+            cap: Some(PairCap { cap_cc, require_noworse }),
+        })
+        .collect();
+    Some(targets)
 }
 
-// Small helper to reuse your existing "one resting order per side"
-fn place_or_manage_resting(
+// Target for the simpler quotes (bootstrap / strong side): no pair-cap re-check on reprice.
+fn simple_target(
     cfg: &Config,
-    ticker: &str,
-    m: &mut Market,
-    now: Instant,
+    m: &Market,
     side: Side,
     p: u8,
     qty: u64,
     only_reprice_if_more_aggressive: bool
-) -> Option<ExecCommand> {
-    if let Some(existing) = m.resting_hint(side).as_ref().cloned() {
-        let existing_remaining = m.orders.by_client
-            .get(&existing.client_order_id)
-            .map(|r| r.qty.saturating_sub(r.filled_qty))
-            .unwrap_or(1);
-        
-        let want_upsize = qty > existing_remaining;
-        if existing.price_cents == p && !want_upsize {
-            return None;
-        }
-
-        let age_ms = now.duration_since(existing.created_at).as_millis() as u64;
-        if age_ms < cfg.min_resting_life_ms {
-            return None;
-        }
-
-        if let Some(t0) = existing.cancel_requested_at {
-            let since = now.duration_since(t0).as_millis() as u64;
-            if since < cfg.cancel_retry_ms {
-                return None;
-            }
-        }
-
-        let drift = existing.price_cents.abs_diff(p);
-        let drift_threshold = drift_threshold_cents(cfg, m, side);
-        
-        // For buys: higher bid is more aggressive
-        let more_aggressive = p > existing.price_cents;
-
-        let drift_triggers_cancel = if only_reprice_if_more_aggressive {
-            // Only cancel/replace if we're moving UP (more aggressive) by threshold
-            more_aggressive && drift >= drift_threshold
-        } else {
-            drift >= drift_threshold
-        };
-        
-        if drift_triggers_cancel || want_upsize {
-            let Some(order_id) = existing.order_id.clone() else {
-                return None;
-            };
-            if let Some(hm) = m.resting_hint_mut(side).as_mut() {
-                hm.cancel_requested_at = Some(now);
-            }
-            return Some(ExecCommand::CancelOrder {
-                ticker: ticker.to_string(),
-                order_id,
-            });
-        }
-        return None;
-    }
-
-    let (client_order_id, cmd) = stage_place_order(
-        ticker, m, now, side, p, qty.max(1), Tif::Gtc, true
-    );
-
-    let queue_ahead = match side {
-        Side::Yes => m.book.yes_bids[p as usize],
-        Side::No => m.book.no_bids[p as usize],
-    };
-    *m.resting_hint_mut(side) = Some(RestingHint {
-        side,
+) -> QuoteTarget {
+    QuoteTarget {
         price_cents: p,
-        created_at: now,
-        cancel_requested_at: None,
-        client_order_id,
-        order_id: None,
-        // PAPER TRADING
-        queue_ahead,
-    });
-
-    Some(cmd)
+        qty: qty.max(1),
+        drift_cents: drift_threshold_cents(cfg, m, side),
+        sticky_down: only_reprice_if_more_aggressive,
+        cap: None,
+    }
 }
 
-pub fn decide(cfg: &Config, ticker: &str, m: &mut Market) -> Option<ExecCommand> {
+/// What we want working on this ticker right now. `order_manager::reconcile`
+/// turns it into commands.
+pub fn decide(cfg: &Config, ticker: &str, m: &mut Market, now: Instant) -> DesiredState {
     let now_s = unix_now_s();
-    let now = Instant::now();
 
     // If market_manager already told us close_ts, stop trading after that.
     if let Some(close_ts) = m.close_ts {
         if now_s >= close_ts {
-            return None;
+            return DesiredState::default();
         }
     }
 
@@ -1258,8 +1132,24 @@ pub fn decide(cfg: &Config, ticker: &str, m: &mut Market) -> Option<ExecCommand>
 
     // Mode uses actual window_s (not cfg.window_s).
     m.mode = pick_mode(cfg, t_rem, window_s);
+
+    // Quotes that hit their expiration_ts are gone on the exchange; forget them here too.
+    let expired = m.expire_orders(now_s);
+    if expired > 0 {
+        debug!(ticker = %ticker, expired, "orders expired");
+    }
     // println!("Current Mode: {:#?}", m.mode);
 
+    // Early pair lock-in owns the ticker while it runs (no new buys underneath it).
+    if let Some(desired) = super::lockin::maybe_pair_lockin(cfg, ticker, m, now) {
+        return desired;
+    }
+    if m.lockin.is_some() {
+        return DesiredState::pull_all();
+    }
+
+    let mut desired = DesiredState::default();
+
     let desired_side = if has_pair(m) {
         choose_working_side_simple(cfg, m, t_rem)
     } else {
@@ -1285,28 +1175,27 @@ pub fn decide(cfg: &Config, ticker: &str, m: &mut Market) -> Option<ExecCommand>
         desired_side
     };
 
-    // if must-balance, proactively cancel the non-hedge side maker (if present)
+    // if must-balance, proactively pull the non-hedge side maker (if present)
     if has_pair(m) && must_balance {
         let hedge = hedge_side(m);
-        let wrong_side = hedge.other();
-        if let Some(cmd) = cancel_side_if_allowed(cfg, ticker, m, now, wrong_side) {
-            return Some(cmd);
-        }
+        *desired.quote_mut(hedge.other()) = QuoteIntent::Pull;
     }
 
-    // 0) Cancel stale resting orders (but never churn fast).
-    if let Some(cmd) = cancel_stale_if_needed(cfg, ticker, m, now) {
-        return Some(cmd);
+    // 1) Opportunistic taker (cost-driven): if ask is cheap enough to improve/keep caps.
+    //    Quotes are re-planned next tick against the post-fill position.
+    if let Some(taker) = maybe_opportunistic_taker(cfg, m, now, t_rem, window_s, desired_side) {
+        desired.takers.push(taker);
+        return desired;
     }
 
-    // 1) Opportunistic taker (cost-driven): if ask is cheap enough to improve/keep caps.
-    if let Some(cmd) = maybe_opportunistic_taker(cfg, ticker, m, now, t_rem, window_s, desired_side) {
-        return Some(cmd);
+    // Past the quote expiry cutoff: takers only.
+    if quotes_closed(cfg, m, now_s) {
+        return desired;
     }
 
-    // 2) Maker quoting on desired side (resting) with churn control.
-    if let Some(cmd) = maybe_maker_quote(cfg, ticker, m, now, t_rem, window_s, primary_side) {
-        return Some(cmd);
+    // 2) Maker quoting on desired side (resting).
+    if let Some(rungs) = maybe_maker_quote(cfg, m, t_rem, window_s, primary_side) {
+        *desired.quote_mut(primary_side) = QuoteIntent::Ladder(rungs);
     }
 
     // 3) dual quote: keep the other side quoted too
@@ -1328,27 +1217,28 @@ pub fn decide(cfg: &Config, ticker: &str, m: &mut Market) -> Option<ExecCommand>
             1
         };
 
-        let would = m.pos.simulate_buy(other, 0, other_qty);
+        let would = m.pos.simulate_buy(other, 0, other_qty, Liquidity::Maker);
         if would.imbalance_ratio() <= imbalance_cap {
-            if skew {
+            let target = if skew {
                 // Strong side quote: only if it material improves pair-cost
-                if let Some(p_strong) = passive_strong_price(cfg, m, other) {
-                    return place_or_manage_resting(
+                passive_strong_price(cfg, m, other).map(|p_strong| {
+                    vec![simple_target(
                         cfg,
-                        ticker,
                         m,
-                        now,
                         other,
                         p_strong,
                         cfg.dual_strong_qty,
                         true, // sticky on downward moves
-                    );
-                }
+                    )]
+                })
             } else {
-            // Near-balanced: normal quote on the other side too
-            return maybe_maker_quote(cfg, ticker, m, now, t_rem, window_s, other);
+                // Near-balanced: normal quote on the other side too
+                maybe_maker_quote(cfg, m, t_rem, window_s, other)
+            };
+            if let Some(rungs) = target {
+                *desired.quote_mut(other) = QuoteIntent::Ladder(rungs);
             }
         }
     }
-    None
+    desired
 }
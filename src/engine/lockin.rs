@@ -0,0 +1,293 @@
+//! Early pair lock-in.
+//!
+//! Holding N matched YES/NO pairs at pair cost C pays N * ($1 - C) at settlement.
+//! If best_bid(YES) + best_bid(NO) > C + fees right now, selling both legs locks in
+//! the same edge immediately and frees the capital to be recycled inside the window.
+//!
+//! We exit with IOC sells at the bids, one leg at a time (thinner bid first, since it's
+//! the one most likely to disappear). Between legs we're carrying naked inventory, so we
+//! track that leg risk and give up after `lockin_leg_timeout_ms` (normal hedging takes over).
+//! With nothing naked, a failed price check abandons the exit at once, and no lock-in
+//! outlives `lockin_max_duration_ms`, so the ticker always gets back to normal trading.
+
+use std::time::Instant;
+
+use tracing::{debug, info, warn};
+
+use crate::config::Config;
+use crate::state::orders::OrderStatus;
+use crate::state::ticker::Market;
+use crate::types::{Action, PairLockIn, Side, CC_PER_CENT};
+
+use super::order_manager::{DesiredState, TakerIntent};
+
+fn bid_qty(m: &Market, side: Side, price: u8) -> i64 {
+    m.book.level_qty(side, price)
+}
+
+/// Taker fee per contract (cc) for selling `pairs` contracts at `price`.
+fn sell_fee_cc(m: &Market, price: u8, pairs: i64) -> i64 {
+    let n = pairs.max(1);
+    m.pos.fee_model.taker_fee_cc(price, n as u64) / n
+}
+
+/// Edge per pair (cc) if we sold `pairs` YES and NO at the current best bids, after fees.
+fn lockin_edge_cc(m: &Market, pair_cc: i64, pairs: i64) -> Option<i64> {
+    let by = m.book.best_bid(Side::Yes)?;
+    let bn = m.book.best_bid(Side::No)?;
+    let fees_cc = sell_fee_cc(m, by, pairs) + sell_fee_cc(m, bn, pairs);
+    Some((by as i64 + bn as i64) * CC_PER_CENT - pair_cc - fees_cc)
+}
+
+/// Should we start a lock-in, and for how many pairs?
+fn plan_lockin(cfg: &Config, m: &Market) -> Option<(i64, i64)> {
+    let matched = m.pos.yes_qty.min(m.pos.no_qty);
+    if matched <= 0 {
+        return None;
+    }
+
+    // Don't plan more than the top of both books can absorb.
+    let by = m.book.best_bid(Side::Yes)?;
+    let bn = m.book.best_bid(Side::No)?;
+    let pairs = matched
+        .min(bid_qty(m, Side::Yes, by))
+        .min(bid_qty(m, Side::No, bn))
+        .min(cfg.lockin_max_pairs as i64);
+
+    if pairs <= 0 {
+        return None;
+    }
+
+    let pair_cc = m.pos.pair_cost_cc()?;
+    let edge_cc = lockin_edge_cc(m, pair_cc, pairs)?;
+    if edge_cc < cfg.lockin_min_edge_cc {
+        return None;
+    }
+    Some((pairs, edge_cc))
+}
+
+fn sold_qty(m: &Market, lk: &PairLockIn, side: Side) -> i64 {
+    lk.orders
+        .iter()
+        .filter_map(|id| m.orders.by_client.get(id))
+        .filter(|r| r.side == side)
+        .map(|r| r.filled_qty as i64)
+        .sum()
+}
+
+fn leg_in_flight(m: &Market, lk: &PairLockIn) -> bool {
+    lk.orders
+        .iter()
+        .filter_map(|id| m.orders.by_client.get(id))
+        .any(|r| r.status == OrderStatus::PendingAck)
+}
+
+fn last_leg(lk: &PairLockIn, side: Side) -> Option<Instant> {
+    match side {
+        Side::Yes => lk.last_leg_yes,
+        Side::No => lk.last_leg_no,
+    }
+}
+
+/// Start (if profitable) or advance an early pair exit.
+/// Returns what this ticker should have working while a lock-in step is pending.
+pub fn maybe_pair_lockin(cfg: &Config, ticker: &str, m: &mut Market, now: Instant) -> Option<DesiredState> {
+    if !cfg.lockin_enabled {
+        return None;
+    }
+
+    if m.lockin.is_none() {
+        let (pairs, edge_cc) = plan_lockin(cfg, m)?;
+        let entry_pair_cc = m.pos.pair_cost_cc()?;
+        info!(
+            ticker = %ticker,
+            pairs,
+            edge_cc,
+            entry_pair_cc,
+            yes_bid = ?m.book.best_bid(Side::Yes),
+            no_bid = ?m.book.best_bid(Side::No),
+            "lock-in: starting pair exit"
+        );
+        m.lockin = Some(PairLockIn {
+            started_at: now,
+            pairs,
+            entry_pair_cc,
+            orders: Vec::new(),
+            first_leg: None,
+            last_leg_yes: None,
+            last_leg_no: None,
+            leg_risk_since: None,
+        });
+    }
+
+    step_lockin(cfg, ticker, m, now)
+}
+
+fn step_lockin(cfg: &Config, ticker: &str, m: &mut Market, now: Instant) -> Option<DesiredState> {
+    // 0) Overall deadline: whatever state it's in, the lock-in ends here.
+    let started_at = m.lockin.as_ref()?.started_at;
+    if (now.duration_since(started_at).as_millis() as u64) >= cfg.lockin_max_duration_ms {
+        warn!(
+            ticker = %ticker,
+            yes_qty = m.pos.yes_qty,
+            no_qty = m.pos.no_qty,
+            "lock-in: deadline passed; abandoning (hedge logic takes over)"
+        );
+        m.lockin = None;
+        return None;
+    }
+
+    // 1) Pull our resting buys first: selling into our own bid would self-trade.
+    if m.has_working() {
+        return Some(DesiredState::pull_all());
+    }
+
+    let lk = m.lockin.clone()?;
+    let sold_yes = sold_qty(m, &lk, Side::Yes);
+    let sold_no = sold_qty(m, &lk, Side::No);
+    let rem_yes = lk.pairs - sold_yes;
+    let rem_no = lk.pairs - sold_no;
+
+    // 2) Done?
+    if rem_yes <= 0 && rem_no <= 0 {
+        info!(
+            ticker = %ticker,
+            pairs = lk.pairs,
+            realized_cc = m.pos.realized_pnl_cc,
+            elapsed_ms = now.duration_since(lk.started_at).as_millis() as u64,
+            "lock-in: both legs done"
+        );
+        m.lockin = None;
+        return None;
+    }
+
+    // 3) Leg risk bookkeeping: one side sold ahead of the other.
+    let naked = sold_yes != sold_no;
+    let leg_risk_since = match (naked, lk.leg_risk_since) {
+        (true, None) => Some(now),
+        (true, t) => t,
+        (false, _) => None,
+    };
+    if let Some(l) = m.lockin.as_mut() {
+        l.leg_risk_since = leg_risk_since;
+    }
+
+    // Wait for an IOC we already sent to resolve before sending the next one.
+    if leg_in_flight(m, &lk) {
+        return None;
+    }
+
+    // 4) Which leg next?
+    let nothing_sold = sold_yes == 0 && sold_no == 0;
+    let side = if nothing_sold {
+        // Thinner top-of-book first: it's the one most likely to vanish.
+        let by = m.book.best_bid(Side::Yes);
+        let bn = m.book.best_bid(Side::No);
+        match (by, bn) {
+            (Some(py), Some(pn)) => {
+                if bid_qty(m, Side::Yes, py) <= bid_qty(m, Side::No, pn) { Side::Yes } else { Side::No }
+            }
+            _ => {
+                debug!(ticker = %ticker, "lock-in: bids gone before first leg; abandoning");
+                m.lockin = None;
+                return None;
+            }
+        }
+    } else if rem_yes >= rem_no {
+        Side::Yes
+    } else {
+        Side::No
+    };
+    let remaining = match side {
+        Side::Yes => rem_yes,
+        Side::No => rem_no,
+    };
+
+    if let Some(last) = last_leg(&lk, side)
+        && (now.duration_since(last).as_millis() as u64) < cfg.taker_cooldown_ms
+    {
+        return None;
+    }
+
+    let Some(bid) = m.book.best_bid(side) else {
+        return give_up_if_timed_out(cfg, ticker, m, now, leg_risk_since);
+    };
+
+    // 5) Price checks. Until something has actually sold, we're still "before the first leg".
+    match lk.first_leg.filter(|_| !nothing_sold) {
+        None => {
+            // First leg: the edge must still be there, otherwise just drop the idea.
+            let edge_ok = lockin_edge_cc(m, lk.entry_pair_cc, lk.pairs)
+                .is_some_and(|e| e >= cfg.lockin_min_edge_cc);
+            if !edge_ok {
+                debug!(ticker = %ticker, "lock-in: edge gone before first leg; abandoning");
+                m.lockin = None;
+                return None;
+            }
+        }
+        Some((first_side, first_px)) if first_side == side => {
+            // Rest of a partially filled first leg: not cheaper than we planned on.
+            if bid < first_px {
+                return give_up_if_timed_out(cfg, ticker, m, now, leg_risk_since);
+            }
+        }
+        Some((_, first_px)) => {
+            // Second leg: break-even against what the first leg got us.
+            let fees_cc = sell_fee_cc(m, first_px, lk.pairs) + sell_fee_cc(m, bid, lk.pairs);
+            let floor_cc = lk.entry_pair_cc + fees_cc - (first_px as i64) * CC_PER_CENT;
+            if (bid as i64) * CC_PER_CENT < floor_cc {
+                return give_up_if_timed_out(cfg, ticker, m, now, leg_risk_since);
+            }
+        }
+    }
+
+    let qty = remaining.min(bid_qty(m, side, bid)).max(0);
+    if qty == 0 {
+        return None;
+    }
+
+    let taker = TakerIntent::new(side, Action::Sell, bid, qty as u64);
+
+    if let Some(l) = m.lockin.as_mut() {
+        l.orders.push(taker.client_order_id);
+        if nothing_sold {
+            l.first_leg = Some((side, bid));
+        }
+        match side {
+            Side::Yes => l.last_leg_yes = Some(now),
+            Side::No => l.last_leg_no = Some(now),
+        }
+    }
+
+    info!(ticker = %ticker, ?side, price = bid, qty, remaining, "lock-in: sending leg");
+    let mut desired = DesiredState::pull_all();
+    desired.takers.push(taker);
+    Some(desired)
+}
+
+fn give_up_if_timed_out(
+    cfg: &Config,
+    ticker: &str,
+    m: &mut Market,
+    now: Instant,
+    leg_risk_since: Option<Instant>,
+) -> Option<DesiredState> {
+    // Legs even (nothing naked): no reason to wait on the price, just stop.
+    let Some(t0) = leg_risk_since else {
+        debug!(ticker = %ticker, "lock-in: price gone with legs even; abandoning");
+        m.lockin = None;
+        return None;
+    };
+    if (now.duration_since(t0).as_millis() as u64) < cfg.lockin_leg_timeout_ms {
+        return None;
+    }
+
+    warn!(
+        ticker = %ticker,
+        yes_qty = m.pos.yes_qty,
+        no_qty = m.pos.no_qty,
+        "lock-in: second leg not available in time; leaving imbalance to hedge logic"
+    );
+    m.lockin = None;
+    None
+}
@@ -1,2 +1,6 @@
 pub mod task;
-pub mod decision;
\ No newline at end of file
+pub mod decision;
+pub mod lockin;
+pub mod order_manager;
+pub mod portfolio;
+pub mod risk;
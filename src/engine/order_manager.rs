@@ -0,0 +1,316 @@
+//! Desired-state order manager.
+//!
+//! `decide` doesn't emit commands any more. It describes what it wants working on each
+//! side (`QuoteIntent`) plus any immediate IOC orders (`TakerIntent`), and `reconcile`
+//! turns the difference between that and our live orders into this tick's commands:
+//! cancels first, then takers, then amends/places.
+//!
+//! The churn limits are enforced here and only here:
+//! - `min_resting_life_ms`: a quote is never touched before it's this old
+//! - `cancel_retry_ms`: after a cancel/amend request, wait this long before another
+//! - `cancel_stale_ms`: quotes older than this are pulled so they get re-placed fresh
+
+use std::time::Instant;
+
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::fees::Liquidity;
+use crate::state::orders::{OrderRec, OrderStatus};
+use crate::state::ticker::Market;
+use crate::types::{Action, ExecCommand, PlaceParams, Side, Tif, WorkingOrder};
+
+use super::decision::stage_place_order;
+
+/// Pair-cost rule a resting quote must still satisfy if it filled in full.
+#[derive(Debug, Clone, Copy)]
+pub struct PairCap {
+    pub cap_cc: i64,
+    pub require_noworse: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct QuoteTarget {
+    pub price_cents: u8,
+    pub qty: u64,
+    // Reprice only once the target is at least this far from the resting price.
+    pub drift_cents: u8,
+    // Only follow the market up; a lower target leaves the quote alone
+    // unless it no longer satisfies `cap`.
+    pub sticky_down: bool,
+    pub cap: Option<PairCap>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub enum QuoteIntent {
+    /// No opinion: keep whatever is working (the stale rule still applies).
+    #[default]
+    Leave,
+    /// Nothing should be resting on this side.
+    Pull,
+    /// Post-only buys, one per rung (index = rung; rung 0 is the top quote).
+    /// Working rungs past the end of the ladder are pulled.
+    Ladder(Vec<QuoteTarget>),
+}
+
+/// An IOC order to send this tick. The id is chosen up front so callers can track it.
+#[derive(Debug, Clone)]
+pub struct TakerIntent {
+    pub side: Side,
+    pub action: Action,
+    pub price_cents: u8,
+    pub qty: u64,
+    // Spend limit for buys (sent as `buy_max_cost`), so a moved book can't fill
+    // us past the pair-cost budget the engine priced the order against.
+    pub max_cost_cc: Option<i64>,
+    pub client_order_id: Uuid,
+}
+
+impl TakerIntent {
+    pub fn new(side: Side, action: Action, price_cents: u8, qty: u64) -> Self {
+        Self { side, action, price_cents, qty, max_cost_cc: None, client_order_id: Uuid::new_v4() }
+    }
+
+    pub fn with_max_cost_cc(mut self, max_cost_cc: i64) -> Self {
+        self.max_cost_cc = Some(max_cost_cc);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DesiredState {
+    pub yes: QuoteIntent,
+    pub no: QuoteIntent,
+    pub takers: Vec<TakerIntent>,
+}
+
+impl DesiredState {
+    pub fn pull_all() -> Self {
+        Self { yes: QuoteIntent::Pull, no: QuoteIntent::Pull, takers: Vec::new() }
+    }
+
+    pub fn quote(&self, side: Side) -> &QuoteIntent {
+        match side {
+            Side::Yes => &self.yes,
+            Side::No => &self.no,
+        }
+    }
+
+    pub fn quote_mut(&mut self, side: Side) -> &mut QuoteIntent {
+        match side {
+            Side::Yes => &mut self.yes,
+            Side::No => &mut self.no,
+        }
+    }
+}
+
+/// Diff `desired` against what's working for this ticker and stage the commands.
+pub fn reconcile(
+    cfg: &Config,
+    ticker: &str,
+    m: &mut Market,
+    now: Instant,
+    desired: DesiredState,
+) -> Vec<ExecCommand> {
+    let mut cancels = Vec::new();
+    let mut quotes = Vec::new();
+
+    for side in Side::ALL {
+        let intent = desired.quote(side);
+        let rungs: &[QuoteTarget] = match intent {
+            QuoteIntent::Ladder(r) => r,
+            _ => &[],
+        };
+
+        // 1) What's already working: pull, amend, or leave alone.
+        for w in m.working(side).to_vec() {
+            // Not acked yet, too young, or a request is already in flight.
+            let Some(order_id) = w.order_id.clone() else { continue; };
+            if !churn_allows(cfg, &w, now) {
+                continue;
+            }
+
+            let stale = now.duration_since(w.created_at).as_millis() as u64 >= cfg.cancel_stale_ms;
+            let target = rungs.get(w.rung);
+            let pull = match intent {
+                QuoteIntent::Leave => stale,
+                QuoteIntent::Pull => true,
+                QuoteIntent::Ladder(_) => stale || target.is_none(),
+            };
+
+            if pull {
+                if let Some(wm) = m.working_by_client_mut(side, w.client_order_id) {
+                    wm.cancel_requested_at = Some(now);
+                }
+                cancels.push(ExecCommand::Cancel { ticker: ticker.to_string(), order_id, decided_at: now });
+                continue;
+            }
+
+            if let Some(t) = target
+                && let Some(cmd) = amend_if_needed(ticker, m, now, side, &w, order_id, t)
+            {
+                quotes.push(cmd);
+            }
+        }
+
+        // 2) Rungs with nothing working yet.
+        for (rung, t) in rungs.iter().enumerate() {
+            if m.working(side).iter().any(|w| w.rung == rung) {
+                continue;
+            }
+            quotes.push(place_quote(cfg, ticker, m, now, side, rung, t));
+        }
+    }
+
+    let mut out = cancels;
+    for t in desired.takers {
+        out.push(stage_place_order(cfg, m, PlaceParams {
+            ticker: ticker.to_string(),
+            side: t.side,
+            action: t.action,
+            price_cents: t.price_cents,
+            qty: t.qty,
+            tif: Tif::Ioc,
+            post_only: false,
+            reduce_only: false,
+            expiration_ts: None,
+            buy_max_cost_cc: t.max_cost_cc,
+            client_order_id: t.client_order_id,
+            decided_at: now,
+        }));
+    }
+    out.extend(quotes);
+
+    if !out.is_empty() {
+        debug!(ticker = %ticker, n = out.len(), "order manager: commands");
+    }
+    out
+}
+
+fn churn_allows(cfg: &Config, h: &WorkingOrder, now: Instant) -> bool {
+    let age_ms = now.duration_since(h.created_at).as_millis() as u64;
+    if age_ms < cfg.min_resting_life_ms {
+        return false;
+    }
+    match h.cancel_requested_at {
+        Some(t0) => now.duration_since(t0).as_millis() as u64 >= cfg.cancel_retry_ms,
+        None => true,
+    }
+}
+
+fn place_quote(
+    cfg: &Config,
+    ticker: &str,
+    m: &mut Market,
+    now: Instant,
+    side: Side,
+    rung: usize,
+    t: &QuoteTarget,
+) -> ExecCommand {
+    let client_order_id = Uuid::new_v4();
+    let cmd = stage_place_order(cfg, m, PlaceParams {
+        ticker: ticker.to_string(),
+        side,
+        action: Action::Buy,
+        price_cents: t.price_cents,
+        qty: t.qty.max(1),
+        tif: Tif::Gtc,
+        post_only: true,
+        reduce_only: false,
+        expiration_ts: None,
+        buy_max_cost_cc: None,
+        client_order_id,
+        decided_at: now,
+    });
+
+    let queue_ahead = m.book.level_qty(side, t.price_cents);
+    m.working_mut(side).push(WorkingOrder {
+        side,
+        rung,
+        price_cents: t.price_cents,
+        created_at: now,
+        cancel_requested_at: None,
+        client_order_id,
+        order_id: None,
+        // PAPER TRADING
+        queue_ahead,
+        queue_behind: 0,
+    });
+
+    cmd
+}
+
+/// Move a resting quote to the target price/size if it drifted far enough
+/// (or got too small, or stopped satisfying the pair cap).
+fn amend_if_needed(
+    ticker: &str,
+    m: &mut Market,
+    now: Instant,
+    side: Side,
+    existing: &WorkingOrder,
+    order_id: String,
+    t: &QuoteTarget,
+) -> Option<ExecCommand> {
+    let rec = m.orders.by_client.get(&existing.client_order_id)?.clone();
+    let existing_remaining = rec.qty.saturating_sub(rec.filled_qty);
+
+    // Only force a resize if we want MORE (avoids churn when gap shrinks)
+    let want_upsize = t.qty > existing_remaining;
+    if existing.price_cents == t.price_cents && !want_upsize {
+        return None;
+    }
+
+    let drift = existing.price_cents.abs_diff(t.price_cents);
+    // For BUY orders: higher price = more aggressive
+    let more_aggressive = t.price_cents > existing.price_cents;
+
+    // If the existing order filled in full, would it still satisfy the cap?
+    let existing_ok_under_cap = t.cap.is_none_or(|cap| {
+        let sim = m.pos.simulate_buy(side, existing.price_cents, existing_remaining as i64, Liquidity::Maker);
+        match sim.pair_cost_cc() {
+            Some(pc) if pc > cap.cap_cc => false,
+            Some(pc) if cap.require_noworse => m.pos.pair_cost_cc().is_none_or(|old| pc <= old),
+            _ => true,
+        }
+    });
+
+    let drifted = drift >= t.drift_cents;
+    let reprice = if t.sticky_down {
+        // Sticky-down: only chase upward moves, unless the existing order is no longer acceptable.
+        (more_aggressive && drifted) || !existing_ok_under_cap
+    } else {
+        drifted
+    };
+    if !want_upsize && !reprice {
+        return None;
+    }
+
+    // The amended order gets its own record; the working order moves over once the exchange acks.
+    let updated_client_order_id = Uuid::new_v4();
+    m.orders.insert_pending(OrderRec {
+        client_order_id: updated_client_order_id,
+        order_id: None,
+        price_cents: t.price_cents,
+        qty: t.qty.max(1),
+        status: OrderStatus::PendingAck,
+        created_at: now,
+        filled_qty: 0,
+        ..rec.clone()
+    });
+    if let Some(wm) = m.working_by_client_mut(side, existing.client_order_id) {
+        wm.cancel_requested_at = Some(now);
+    }
+
+    Some(ExecCommand::Amend {
+        ticker: ticker.to_string(),
+        side,
+        action: rec.action,
+        order_id,
+        client_order_id: existing.client_order_id,
+        updated_client_order_id,
+        price_cents: t.price_cents,
+        count: rec.filled_qty + t.qty.max(1),
+        decided_at: now,
+    })
+}
@@ -0,0 +1,196 @@
+//! Portfolio-wide exposure limits.
+//!
+//! Tickers are decided one at a time, so on their own nothing bounds the capital at risk
+//! across several series. Each engine pass sums every ticker's position in `Shared`:
+//! - cost basis: what we paid for everything we hold (fees included)
+//! - unhedged: |yes_qty - no_qty| at the long side's average price
+//! - worst-case settlement loss: cost - min(yes_qty, no_qty) * $1 - realized PnL
+//!
+//! Over any limit, new accumulation stops everywhere (`throttle`): only buys that shrink a
+//! ticker's imbalance, up to the gap, and sells go out. Existing accumulation quotes are pulled.
+
+use std::collections::HashMap;
+
+use crate::config::Config;
+use crate::state::position::Position;
+use crate::state::Shared;
+use crate::types::{Action, Side};
+
+use super::order_manager::{DesiredState, QuoteIntent};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Exposure {
+    pub cost_cc: i64,
+    pub unhedged_cc: i64,
+    pub worst_loss_cc: i64,
+}
+
+impl Exposure {
+    pub fn of(pos: &Position) -> Self {
+        let cost_cc = pos.yes_cost_cc + pos.no_cost_cc;
+        let unhedged_cc = if pos.yes_qty > pos.no_qty {
+            (pos.yes_qty - pos.no_qty) * pos.avg_yes_cc().unwrap_or(0)
+        } else {
+            (pos.no_qty - pos.yes_qty) * pos.avg_no_cc().unwrap_or(0)
+        };
+        let worst_loss_cc = (-pos.worst_case_pnl_cc()).max(0);
+        Self { cost_cc, unhedged_cc, worst_loss_cc }
+    }
+
+    pub fn plus(self, o: Exposure) -> Self {
+        Self {
+            cost_cc: self.cost_cc + o.cost_cc,
+            unhedged_cc: self.unhedged_cc + o.unhedged_cc,
+            worst_loss_cc: self.worst_loss_cc + o.worst_loss_cc,
+        }
+    }
+
+    pub fn minus(self, o: Exposure) -> Self {
+        Self {
+            cost_cc: self.cost_cc - o.cost_cc,
+            unhedged_cc: self.unhedged_cc - o.unhedged_cc,
+            worst_loss_cc: self.worst_loss_cc - o.worst_loss_cc,
+        }
+    }
+
+    /// The first portfolio limit this exposure is at or over, if any.
+    pub fn breached(&self, cfg: &Config) -> Option<&'static str> {
+        if self.cost_cc >= cfg.portfolio_max_cost_cc {
+            Some("max_cost")
+        } else if self.unhedged_cc >= cfg.portfolio_max_unhedged_cc {
+            Some("max_unhedged")
+        } else if self.worst_loss_cc >= cfg.portfolio_max_worst_loss_cc {
+            Some("max_worst_loss")
+        } else {
+            None
+        }
+    }
+}
+
+/// Exposure per ticker, as of now.
+pub async fn snapshot(shared: &Shared) -> HashMap<String, Exposure> {
+    let tickers: Vec<_> = shared.tickers.iter().map(|r| (r.key().clone(), r.value().clone())).collect();
+    let mut out = HashMap::with_capacity(tickers.len());
+    for (ticker, ts) in tickers {
+        let e = Exposure::of(&ts.mkt.read().await.pos);
+        out.insert(ticker, e);
+    }
+    out
+}
+
+/// Strip accumulation from `desired`: keep sells, and buys on the short side up to the gap.
+pub fn throttle(desired: &mut DesiredState, pos: &Position) {
+    let (hedge, mut gap) = if pos.yes_qty < pos.no_qty {
+        (Some(Side::Yes), (pos.no_qty - pos.yes_qty) as u64)
+    } else if pos.no_qty < pos.yes_qty {
+        (Some(Side::No), (pos.yes_qty - pos.no_qty) as u64)
+    } else {
+        (None, 0)
+    };
+
+    desired.takers.retain_mut(|t| {
+        if t.action == Action::Sell {
+            return true;
+        }
+        if Some(t.side) != hedge || gap == 0 {
+            return false;
+        }
+        t.qty = t.qty.min(gap);
+        gap -= t.qty;
+        true
+    });
+
+    for side in Side::ALL {
+        let q = desired.quote_mut(side);
+        if Some(side) != hedge {
+            *q = QuoteIntent::Pull;
+            continue;
+        }
+        if let QuoteIntent::Ladder(rungs) = q {
+            // Rungs are planned as if they all fill, so cap their running total at the gap.
+            let mut left = gap;
+            rungs.retain_mut(|r| {
+                r.qty = r.qty.min(left);
+                left -= r.qty;
+                r.qty > 0
+            });
+            if rungs.is_empty() {
+                *q = QuoteIntent::Pull;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::order_manager::{QuoteTarget, TakerIntent};
+
+    fn pos(yes_qty: i64, no_qty: i64) -> Position {
+        Position { yes_qty, no_qty, ..Position::default() }
+    }
+
+    fn taker(side: Side, action: Action, qty: u64) -> TakerIntent {
+        TakerIntent { side, action, price_cents: 40, qty, max_cost_cc: None, client_order_id: uuid::Uuid::new_v4() }
+    }
+
+    fn ladder(qtys: &[u64]) -> QuoteIntent {
+        let rung = |&qty| QuoteTarget { price_cents: 40, qty, drift_cents: 1, sticky_down: false, cap: None };
+        QuoteIntent::Ladder(qtys.iter().map(rung).collect())
+    }
+
+    fn rung_qtys(q: &QuoteIntent) -> Option<Vec<u64>> {
+        match q {
+            QuoteIntent::Ladder(rungs) => Some(rungs.iter().map(|r| r.qty).collect()),
+            _ => None,
+        }
+    }
+
+    fn taker_qtys(d: &DesiredState) -> Vec<(Side, Action, u64)> {
+        d.takers.iter().map(|t| (t.side, t.action, t.qty)).collect()
+    }
+
+    #[test]
+    fn balanced_position_only_sells() {
+        let mut d = DesiredState {
+            yes: ladder(&[3]),
+            no: ladder(&[3]),
+            takers: vec![taker(Side::Yes, Action::Buy, 2), taker(Side::No, Action::Sell, 2)],
+        };
+        throttle(&mut d, &pos(4, 4));
+        assert!(matches!(d.yes, QuoteIntent::Pull));
+        assert!(matches!(d.no, QuoteIntent::Pull));
+        assert_eq!(taker_qtys(&d), vec![(Side::No, Action::Sell, 2)]);
+    }
+
+    #[test]
+    fn hedge_buys_capped_at_the_gap() {
+        // Long No by 5: only Yes buys, 5 in total, takers first.
+        let mut d = DesiredState {
+            yes: ladder(&[2, 2, 2]),
+            no: ladder(&[2]),
+            takers: vec![
+                taker(Side::Yes, Action::Buy, 2),
+                taker(Side::No, Action::Buy, 2),
+                taker(Side::Yes, Action::Sell, 1),
+            ],
+        };
+        throttle(&mut d, &pos(3, 8));
+        assert_eq!(taker_qtys(&d), vec![(Side::Yes, Action::Buy, 2), (Side::Yes, Action::Sell, 1)]);
+        assert_eq!(rung_qtys(&d.yes), Some(vec![2, 1]));
+        assert!(matches!(d.no, QuoteIntent::Pull));
+    }
+
+    #[test]
+    fn takers_that_close_the_gap_pull_the_ladder() {
+        let mut d = DesiredState {
+            yes: ladder(&[1]),
+            no: ladder(&[4, 4]),
+            takers: vec![taker(Side::No, Action::Buy, 4), taker(Side::No, Action::Buy, 4)],
+        };
+        throttle(&mut d, &pos(6, 1));
+        assert_eq!(taker_qtys(&d), vec![(Side::No, Action::Buy, 4), (Side::No, Action::Buy, 1)]);
+        assert!(matches!(d.no, QuoteIntent::Pull));
+        assert!(matches!(d.yes, QuoteIntent::Pull));
+    }
+}
@@ -0,0 +1,400 @@
+//! Pre-trade risk gate.
+//!
+//! Every command `reconcile` produces goes through `gate` before it reaches exec. This
+//! doesn't second-guess the strategy; it stops orders no strategy should send:
+//! - notional: a buy's price * qty above `risk_max_order_notional_cc` (the engine sizes buys
+//!   under it with `max_qty_at`; sells only unwind what we hold, so they're exempt)
+//! - price band: buys above the ask + band (or over `max_buy_price_cents`), sells below
+//!   the bid - band; with no book to compare against, nothing goes out
+//! - rate: more than `risk_max_orders_per_s` places/amends on the ticker in the last second
+//! - open orders: a place beyond `risk_max_open_orders` live orders on the ticker
+//! - duplicates: a `client_order_id` we've sent before, or a second live order at the same
+//!   side/action/price
+//! - time: outside the window (`open_ts`..`close_ts`), or window times unknown
+//!
+//! Cancels always pass. A rejected command is rolled back locally the same way a failed
+//! request is (record Rejected, working order forgotten), logged with its reason and counted.
+
+use std::collections::{HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::state::orders::OrderStatus;
+use crate::state::ticker::Market;
+use crate::types::{Action, ExecCommand, Side, CC_PER_CENT};
+
+use super::decision::unix_now_s;
+
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RiskReject {
+    Notional,
+    PriceBand,
+    NoBook,
+    Rate,
+    OpenOrders,
+    DuplicateId,
+    DuplicatePrice,
+    OutsideWindow,
+}
+
+impl RiskReject {
+    const COUNT: usize = 8;
+
+    fn idx(self) -> usize {
+        self as usize
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RiskReject::Notional => "max_notional",
+            RiskReject::PriceBand => "price_band",
+            RiskReject::NoBook => "no_book",
+            RiskReject::Rate => "order_rate",
+            RiskReject::OpenOrders => "max_open_orders",
+            RiskReject::DuplicateId => "duplicate_client_order_id",
+            RiskReject::DuplicatePrice => "duplicate_price",
+            RiskReject::OutsideWindow => "outside_window",
+        }
+    }
+}
+
+/// Per-ticker gate state (lives in `Market`).
+#[derive(Debug, Clone, Default)]
+pub struct RiskState {
+    // Places/amends that passed within the last RATE_WINDOW.
+    recent: VecDeque<Instant>,
+    seen_ids: HashSet<Uuid>,
+    rejected: [u64; RiskReject::COUNT],
+}
+
+impl RiskState {
+    pub fn rejected(&self, reason: RiskReject) -> u64 {
+        self.rejected[reason.idx()]
+    }
+}
+
+/// The parts of a place/amend the checks look at.
+struct OrderCheck {
+    side: Side,
+    action: Action,
+    price_cents: u8,
+    qty: u64,
+    client_order_id: Uuid,
+    // Amends: the order being replaced (it's allowed to sit at the same price).
+    replaces: Option<Uuid>,
+    is_place: bool,
+}
+
+impl OrderCheck {
+    fn of(cmd: &ExecCommand) -> Option<Self> {
+        match cmd {
+            ExecCommand::Place(p) => Some(Self {
+                side: p.side,
+                action: p.action,
+                price_cents: p.price_cents,
+                qty: p.qty,
+                client_order_id: p.client_order_id,
+                replaces: None,
+                is_place: true,
+            }),
+            ExecCommand::Amend {
+                side, action, price_cents, count, client_order_id, updated_client_order_id, ..
+            } => Some(Self {
+                side: *side,
+                action: *action,
+                price_cents: *price_cents,
+                qty: *count,
+                client_order_id: *updated_client_order_id,
+                replaces: Some(*client_order_id),
+                is_place: false,
+            }),
+            ExecCommand::Cancel { .. } => None,
+        }
+    }
+}
+
+fn check(cfg: &Config, m: &Market, o: &OrderCheck, now_s: i64) -> Result<(), RiskReject> {
+    match (m.open_ts, m.close_ts) {
+        (_, None) => return Err(RiskReject::OutsideWindow),
+        (open, Some(close)) if now_s >= close || open.is_some_and(|t| now_s < t) => {
+            return Err(RiskReject::OutsideWindow);
+        }
+        _ => {}
+    }
+
+    if o.action == Action::Buy && o.qty > max_qty_at(cfg, o.price_cents) {
+        return Err(RiskReject::Notional);
+    }
+
+    let band = cfg.risk_price_band_cents;
+    match o.action {
+        Action::Buy => {
+            let reference = m.book.implied_ask(o.side).or(m.book.best_bid(o.side)).ok_or(RiskReject::NoBook)?;
+            if o.price_cents > cfg.max_buy_price_cents || o.price_cents > reference.saturating_add(band) {
+                return Err(RiskReject::PriceBand);
+            }
+        }
+        Action::Sell => {
+            let reference = m.book.best_bid(o.side).or(m.book.implied_ask(o.side)).ok_or(RiskReject::NoBook)?;
+            if o.price_cents < reference.saturating_sub(band) {
+                return Err(RiskReject::PriceBand);
+            }
+        }
+    }
+
+    if m.risk.seen_ids.contains(&o.client_order_id) {
+        return Err(RiskReject::DuplicateId);
+    }
+
+    // `gate` has already dropped samples older than RATE_WINDOW.
+    if m.risk.recent.len() >= cfg.risk_max_orders_per_s {
+        return Err(RiskReject::Rate);
+    }
+
+    // Other live orders (this one is already staged as PendingAck).
+    let mut open = 0usize;
+    for rec in m.orders.by_client.values() {
+        if rec.client_order_id == o.client_order_id
+            || !matches!(rec.status, OrderStatus::Resting | OrderStatus::PendingAck)
+        {
+            continue;
+        }
+        open += 1;
+        if Some(rec.client_order_id) != o.replaces
+            && rec.side == o.side
+            && rec.action == o.action
+            && rec.price_cents == o.price_cents
+        {
+            return Err(RiskReject::DuplicatePrice);
+        }
+    }
+    if o.is_place && open >= cfg.risk_max_open_orders {
+        return Err(RiskReject::OpenOrders);
+    }
+
+    Ok(())
+}
+
+/// Most contracts one buy at `price_cents` can carry under `risk_max_order_notional_cc`.
+pub fn max_qty_at(cfg: &Config, price_cents: u8) -> u64 {
+    match price_cents {
+        0 => u64::MAX,
+        p => (cfg.risk_max_order_notional_cc / (p as i64 * CC_PER_CENT)).max(0) as u64,
+    }
+}
+
+/// Undo what `reconcile` staged for a command that won't be sent.
+fn roll_back(m: &mut Market, o: &OrderCheck) {
+    m.orders.set_status_by_client(o.client_order_id, OrderStatus::Rejected);
+    if o.is_place {
+        m.forget_working_client(o.client_order_id);
+    }
+    // Amends: the old order stays working; `cancel_requested_at` holds off a retry.
+}
+
+/// A command that passed the gate but never reached exec (queue full or closed): undo it
+/// like a reject. Cancels need nothing; `cancel_retry_ms` sends them again.
+pub fn roll_back_unsent(m: &mut Market, cmd: &ExecCommand) {
+    if let Some(o) = OrderCheck::of(cmd) {
+        roll_back(m, &o);
+    }
+}
+
+/// Pass through the commands that clear every check; reject (and roll back) the rest.
+pub fn gate(cfg: &Config, ticker: &str, m: &mut Market, now: Instant, cmds: Vec<ExecCommand>) -> Vec<ExecCommand> {
+    let now_s = unix_now_s();
+    while m.risk.recent.front().is_some_and(|&t| now.duration_since(t) > RATE_WINDOW) {
+        m.risk.recent.pop_front();
+    }
+
+    let mut out = Vec::with_capacity(cmds.len());
+    for cmd in cmds {
+        let Some(o) = OrderCheck::of(&cmd) else {
+            out.push(cmd);
+            continue;
+        };
+
+        let res = check(cfg, m, &o, now_s);
+        m.risk.seen_ids.insert(o.client_order_id);
+        match res {
+            Ok(()) => {
+                m.risk.recent.push_back(now);
+                out.push(cmd);
+            }
+            Err(reason) => {
+                m.risk.rejected[reason.idx()] += 1;
+                warn!(
+                    ticker,
+                    reason = reason.as_str(),
+                    count = m.risk.rejected(reason),
+                    side = ?o.side,
+                    action = %o.action,
+                    price_cents = o.price_cents,
+                    qty = o.qty,
+                    amend = !o.is_place,
+                    "risk: command rejected"
+                );
+                roll_back(m, &o);
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::decision::stage_place_order;
+    use crate::types::{PlaceParams, Tif};
+
+    /// A ticker mid-window with Yes bid 40 / No bid 55 (Yes ask 45, No ask 60).
+    fn market() -> Market {
+        let mut m = Market::new();
+        let now_s = unix_now_s();
+        m.open_ts = Some(now_s - 60);
+        m.close_ts = Some(now_s + 600);
+        m.book.reset(1, &[(40, 100)], &[(55, 100)]);
+        m
+    }
+
+    fn place(cfg: &Config, m: &mut Market, side: Side, action: Action, price_cents: u8, qty: u64) -> ExecCommand {
+        stage_place_order(cfg, m, PlaceParams {
+            ticker: "T".to_string(),
+            side,
+            action,
+            price_cents,
+            qty,
+            tif: Tif::Ioc,
+            post_only: false,
+            reduce_only: false,
+            expiration_ts: None,
+            buy_max_cost_cc: None,
+            client_order_id: Uuid::new_v4(),
+            decided_at: Instant::now(),
+        })
+    }
+
+    /// Gate one command; the reject reason if it was dropped.
+    fn verdict(cfg: &Config, m: &mut Market, cmd: ExecCommand) -> Option<RiskReject> {
+        let id = OrderCheck::of(&cmd).unwrap().client_order_id;
+        let before = m.risk.rejected;
+        if !gate(cfg, "T", m, Instant::now(), vec![cmd]).is_empty() {
+            return None;
+        }
+        assert_eq!(m.orders.by_client[&id].status, OrderStatus::Rejected);
+        let idx = (0..RiskReject::COUNT).find(|&i| m.risk.rejected[i] != before[i]).unwrap();
+        Some(ALL[idx])
+    }
+
+    const ALL: [RiskReject; RiskReject::COUNT] = [
+        RiskReject::Notional,
+        RiskReject::PriceBand,
+        RiskReject::NoBook,
+        RiskReject::Rate,
+        RiskReject::OpenOrders,
+        RiskReject::DuplicateId,
+        RiskReject::DuplicatePrice,
+        RiskReject::OutsideWindow,
+    ];
+
+    #[test]
+    fn notional_and_price_band() {
+        let cfg = Config::default();
+        let mut m = market();
+
+        let cmd = place(&cfg, &mut m, Side::Yes, Action::Buy, 45, 60);
+        assert_eq!(verdict(&cfg, &mut m, cmd), Some(RiskReject::Notional));
+
+        let cmd = place(&cfg, &mut m, Side::Yes, Action::Buy, 51, 1);
+        assert_eq!(verdict(&cfg, &mut m, cmd), Some(RiskReject::PriceBand));
+        let cmd = place(&cfg, &mut m, Side::Yes, Action::Buy, 50, 1);
+        assert_eq!(verdict(&cfg, &mut m, cmd), None);
+
+        let cmd = place(&cfg, &mut m, Side::Yes, Action::Sell, 34, 1);
+        assert_eq!(verdict(&cfg, &mut m, cmd), Some(RiskReject::PriceBand));
+        let cmd = place(&cfg, &mut m, Side::Yes, Action::Sell, 35, 1);
+        assert_eq!(verdict(&cfg, &mut m, cmd), None);
+
+        let cfg = Config { max_buy_price_cents: 42, ..Config::default() };
+        let cmd = place(&cfg, &mut m, Side::Yes, Action::Buy, 43, 1);
+        assert_eq!(verdict(&cfg, &mut m, cmd), Some(RiskReject::PriceBand));
+    }
+
+    #[test]
+    fn default_sized_orders_fit_the_notional_cap() {
+        let cfg = Config::default();
+        let mut m = market();
+        m.book.reset(2, &[(25, 100)], &[(28, 100)]);
+
+        // A full max_order_qty buy at 70c (Yes ask 72).
+        let cmd = place(&cfg, &mut m, Side::Yes, Action::Buy, 70, cfg.max_order_qty);
+        assert_eq!(verdict(&cfg, &mut m, cmd), None);
+        assert!(max_qty_at(&cfg, cfg.max_buy_price_cents) >= cfg.max_order_qty);
+
+        // Sells only unwind what we hold: no notional cap (a lock-in leg of 100 at 25c).
+        let cmd = place(&cfg, &mut m, Side::Yes, Action::Sell, 25, 100);
+        assert_eq!(verdict(&cfg, &mut m, cmd), None);
+    }
+
+    #[test]
+    fn no_book_or_outside_window() {
+        let cfg = Config::default();
+        let mut m = market();
+        m.book.reset(2, &[], &[]);
+        let cmd = place(&cfg, &mut m, Side::No, Action::Buy, 50, 1);
+        assert_eq!(verdict(&cfg, &mut m, cmd), Some(RiskReject::NoBook));
+
+        let mut m = market();
+        m.close_ts = Some(unix_now_s() - 1);
+        let cmd = place(&cfg, &mut m, Side::No, Action::Buy, 50, 1);
+        assert_eq!(verdict(&cfg, &mut m, cmd), Some(RiskReject::OutsideWindow));
+
+        let mut m = market();
+        m.close_ts = None;
+        let cmd = place(&cfg, &mut m, Side::No, Action::Buy, 50, 1);
+        assert_eq!(verdict(&cfg, &mut m, cmd), Some(RiskReject::OutsideWindow));
+    }
+
+    #[test]
+    fn duplicates() {
+        let cfg = Config::default();
+        let mut m = market();
+
+        let first = place(&cfg, &mut m, Side::No, Action::Buy, 50, 1);
+        assert_eq!(verdict(&cfg, &mut m, first.clone()), None);
+
+        let cmd = place(&cfg, &mut m, Side::No, Action::Buy, 50, 2);
+        assert_eq!(verdict(&cfg, &mut m, cmd), Some(RiskReject::DuplicatePrice));
+        // Same price, other action: fine.
+        let cmd = place(&cfg, &mut m, Side::No, Action::Sell, 50, 1);
+        assert_eq!(verdict(&cfg, &mut m, cmd), None);
+
+        assert_eq!(verdict(&cfg, &mut m, first), Some(RiskReject::DuplicateId));
+    }
+
+    #[test]
+    fn rate_and_open_orders() {
+        let cfg = Config { risk_max_orders_per_s: 2, ..Config::default() };
+        let mut m = market();
+        for p in [40, 41] {
+            let cmd = place(&cfg, &mut m, Side::No, Action::Buy, p, 1);
+            assert_eq!(verdict(&cfg, &mut m, cmd), None);
+        }
+        let cmd = place(&cfg, &mut m, Side::No, Action::Buy, 42, 1);
+        assert_eq!(verdict(&cfg, &mut m, cmd), Some(RiskReject::Rate));
+
+        let cfg = Config { risk_max_open_orders: 2, ..Config::default() };
+        let mut m = market();
+        for p in [40, 41] {
+            let cmd = place(&cfg, &mut m, Side::No, Action::Buy, p, 1);
+            assert_eq!(verdict(&cfg, &mut m, cmd), None);
+        }
+        let cmd = place(&cfg, &mut m, Side::No, Action::Buy, 42, 1);
+        assert_eq!(verdict(&cfg, &mut m, cmd), Some(RiskReject::OpenOrders));
+    }
+}
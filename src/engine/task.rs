@@ -1,19 +1,57 @@
+use std::time::Instant;
+
 use anyhow::Result;
 use tokio::sync::mpsc;
 use tokio::time::{self, Duration};
+use tracing::{info, warn};
 
 use crate::config::Config;
+use crate::engine::order_manager::DesiredState;
+use crate::engine::portfolio::{self, Exposure};
+use crate::state::ticker::Market;
 use crate::state::Shared;
 use crate::types::ExecCommand;
 
 pub async fn run_engine(cfg: Config, shared: Shared, tx: mpsc::Sender<ExecCommand>) -> Result<()> {
     let mut interval = time::interval(Duration::from_millis(cfg.tick_ms));
+    // Portfolio limit currently throttling accumulation (logged on change).
+    let mut throttled: Option<&'static str> = None;
 
     loop {
         let interval_fired = tokio::select! {
             _ = interval.tick() => true,
             _ = shared.notify.notified() => false,
         };
+        // Shutdown: stop producing new commands; main takes over cancels + results.
+        if shared.is_shutting_down() {
+            return Ok(());
+        }
+
+        // Loss governor halt (or a stale feed, per ticker): pull every quote, send nothing new.
+        let halted = shared.governor().is_halted();
+        // Exchange closed or maintenance due (exchange_monitor): same, until it reopens.
+        let trading_allowed = shared.is_trading_allowed();
+
+        // What the account can fund this pass (None in live until the first balance poll).
+        let capital = if cfg.sizing_enabled { Some(crate::sizing::view(&cfg, &shared).await) } else { None };
+
+        let exposures = portfolio::snapshot(&shared).await;
+        let total = exposures.values().fold(Exposure::default(), |a, &e| a.plus(e));
+        let limit = total.breached(&cfg);
+        if limit != throttled {
+            match limit {
+                Some(limit) => warn!(
+                    limit,
+                    cost_cc = total.cost_cc,
+                    unhedged_cc = total.unhedged_cc,
+                    worst_loss_cc = total.worst_loss_cc,
+                    "portfolio limit hit; throttling accumulation"
+                ),
+                None => info!("portfolio back under limits; accumulation resumed"),
+            }
+            throttled = limit;
+        }
+
         for item in shared.tickers.iter() {
             let ticker = item.key().clone();
             let ts = item.value().clone();
@@ -24,14 +62,55 @@ pub async fn run_engine(cfg: Config, shared: Shared, tx: mpsc::Sender<ExecComman
                 continue;
             }
 
-            let cmd = {
+            let cmds = {
                 let mut g = ts.mkt.write().await;
-                crate::engine::decision::decide(&cfg, &ticker, &mut g)
+                let now = Instant::now();
+                // Paused (exchange halt or order rejections): don't stage anything.
+                if g.is_paused(now) {
+                    Vec::new()
+                } else if halted || !trading_allowed || stale_feed(&cfg, &ticker, &mut g, now) {
+                    let cmds = crate::engine::order_manager::reconcile(&cfg, &ticker, &mut g, now, DesiredState::pull_all());
+                    crate::engine::risk::gate(&cfg, &ticker, &mut g, now, cmds)
+                } else {
+                    let mut desired = crate::engine::decision::decide(&cfg, &ticker, &mut g, now);
+                    // Other tickers as of the snapshot, this one as of now.
+                    let others = total.minus(exposures.get(&ticker).copied().unwrap_or_default());
+                    if others.plus(Exposure::of(&g.pos)).breached(&cfg).is_some() {
+                        portfolio::throttle(&mut desired, &g.pos);
+                    }
+                    match &capital {
+                        Some(Some(c)) => crate::sizing::apply(&cfg, c, &ticker, &mut desired, &g, now),
+                        Some(None) => crate::sizing::hold_buys(&mut desired),
+                        None => {}
+                    }
+                    let cmds = crate::engine::order_manager::reconcile(&cfg, &ticker, &mut g, now, desired);
+                    // Nothing reaches exec without passing the risk gate.
+                    crate::engine::risk::gate(&cfg, &ticker, &mut g, now, cmds)
+                }
             };
 
-            if let Some(cmd) = cmd {
-                let _ = tx.try_send(cmd);
+            let mut unsent = Vec::new();
+            for cmd in cmds {
+                if let Err(e) = tx.try_send(cmd) {
+                    let full = matches!(e, mpsc::error::TrySendError::Full(_));
+                    unsent.push((e.into_inner(), full));
+                }
+            }
+            if !unsent.is_empty() {
+                let mut g = ts.mkt.write().await;
+                for (cmd, full) in &unsent {
+                    warn!(ticker = %ticker, queue_full = full, cmd = ?cmd, "exec queue rejected command; rolling back");
+                    crate::engine::risk::roll_back_unsent(&mut g, cmd);
+                }
             }
         }
     }
 }
+
+/// Stale-feed breaker: true while this ticker's book can't be trusted.
+fn stale_feed(cfg: &Config, ticker: &str, m: &mut Market, now: Instant) -> bool {
+    if let Some(reason) = m.feed.check(Duration::from_millis(cfg.feed_stale_ms), now) {
+        warn!(ticker, reason, trips = m.feed.trips, "feed stale; pulling quotes until a fresh snapshot");
+    }
+    m.feed.is_stale()
+}
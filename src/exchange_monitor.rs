@@ -0,0 +1,260 @@
+//! exchange_monitor.rs
+//!
+//! Polls the exchange status, schedule and announcements, pauses/resumes every ticker we
+//! trade and keeps `Shared.trading_allowed` current.
+//!
+//! - Trading off (exchange or trading inactive): every ticker gets an `Exchange` pause,
+//!   which suspends the engine for it until the monitor sees trading back on.
+//! - Trading on: any pause is lifted (order-error and WS pauses would lapse anyway).
+//! - Maintenance: from `maintenance_lead_s` before a scheduled window until it ends, trading
+//!   is not allowed even while the status still says active.
+//! - Not allowed (any of the above): the engine pulls every quote and the market manager
+//!   holds rotation. Both pick up again once trading is allowed.
+//! - Active announcements are logged once each.
+//!
+//! If `cancel_order_on_pause` is set the exchange drops our resting quotes when it pauses,
+//! so our working orders are dropped locally at the same time.
+//!
+//! Shadow profiles are paused and resumed with the main profile.
+
+use std::collections::HashSet;
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use tokio::time::{sleep, Duration};
+use tracing::{info, warn};
+
+use kalshi_rs::KalshiClient;
+
+use crate::config::Config;
+use crate::shadow::ShadowProfile;
+use crate::state::Shared;
+use crate::types::PauseSource;
+
+// kalshi-rs models maintenance windows and announcements as plain strings; the API sends
+// objects, so these two endpoints are parsed here.
+const SCHEDULE_PATH: &str = "/trade-api/v2/exchange/schedule";
+const ANNOUNCEMENTS_PATH: &str = "/trade-api/v2/exchange/announcements";
+
+#[derive(Debug, Deserialize)]
+struct ScheduleResponse {
+    schedule: Schedule,
+}
+
+#[derive(Debug, Deserialize)]
+struct Schedule {
+    #[serde(default)]
+    maintenance_windows: Vec<MaintenanceWindow>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MaintenanceWindow {
+    start_datetime: String,
+    end_datetime: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct AnnouncementsResponse {
+    #[serde(default)]
+    announcements: Vec<Announcement>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Announcement {
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    delivery_time: String,
+    #[serde(default)]
+    status: String,
+}
+
+/// What the monitor knows about the exchange between polls.
+#[derive(Debug)]
+struct ExchangeView {
+    // Last successful status poll: (exchange_active, trading_active).
+    status: (bool, bool),
+    // Scheduled maintenance, epoch seconds [start, end).
+    maintenance: Vec<(i64, i64)>,
+    // Announcements and windows already logged.
+    seen: HashSet<String>,
+    schedule_polled_at: Option<Instant>,
+}
+
+impl ExchangeView {
+    /// Why trading isn't allowed right now, if it isn't.
+    fn blocked(&self, cfg: &Config, now_s: i64) -> Option<&'static str> {
+        match self.status {
+            (false, _) => Some("exchange_inactive"),
+            (_, false) => Some("trading_inactive"),
+            _ if self.maintenance.iter().any(|&(start, end)| now_s >= start - cfg.maintenance_lead_s && now_s < end) => {
+                Some("maintenance")
+            }
+            _ => None,
+        }
+    }
+}
+
+fn parse_utc(ts: &str) -> Result<i64> {
+    Ok(DateTime::parse_from_rfc3339(ts).with_context(|| format!("bad timestamp {ts}"))?.with_timezone(&Utc).timestamp())
+}
+
+async fn poll_schedule(client: &KalshiClient, view: &mut ExchangeView) -> Result<()> {
+    let raw = client.unauthenticated_get(SCHEDULE_PATH).await?;
+    let resp: ScheduleResponse = serde_json::from_str(&raw).with_context(|| format!("parse schedule: {raw}"))?;
+
+    let mut windows = Vec::with_capacity(resp.schedule.maintenance_windows.len());
+    for w in &resp.schedule.maintenance_windows {
+        // One bad entry shouldn't cost us the rest of the schedule.
+        let (start, end) = match (parse_utc(&w.start_datetime), parse_utc(&w.end_datetime)) {
+            (Ok(start), Ok(end)) => (start, end),
+            (Err(e), _) | (_, Err(e)) => {
+                if view.seen.insert(format!("bad|{}|{}", w.start_datetime, w.end_datetime)) {
+                    warn!(err = ?e, "exchange: skipping unparsable maintenance window");
+                }
+                continue;
+            }
+        };
+        if view.seen.insert(format!("maintenance|{start}|{end}")) {
+            warn!(start = %w.start_datetime, end = %w.end_datetime, "exchange: scheduled maintenance");
+        }
+        windows.push((start, end));
+    }
+    view.maintenance = windows;
+    Ok(())
+}
+
+async fn poll_announcements(client: &KalshiClient, view: &mut ExchangeView) -> Result<()> {
+    let raw = client.unauthenticated_get(ANNOUNCEMENTS_PATH).await?;
+    let resp: AnnouncementsResponse = if raw.trim().is_empty() {
+        AnnouncementsResponse::default()
+    } else {
+        serde_json::from_str(&raw).with_context(|| format!("parse announcements: {raw}"))?
+    };
+
+    for a in resp.announcements.iter().filter(|a| a.status == "active") {
+        if !view.seen.insert(format!("announcement|{}|{}", a.delivery_time, a.message)) {
+            continue;
+        }
+        match a.kind.as_str() {
+            "warning" | "error" => warn!(kind = %a.kind, at = %a.delivery_time, "exchange announcement: {}", a.message),
+            _ => info!(kind = %a.kind, at = %a.delivery_time, "exchange announcement: {}", a.message),
+        }
+    }
+    Ok(())
+}
+
+/// Pause every ticker we currently track.
+pub async fn pause_all(cfg: &Config, shared: &Shared, source: PauseSource, retry_at: Option<Instant>) {
+    let now = Instant::now();
+    let tickers: Vec<_> = shared.tickers.iter().map(|r| (r.key().clone(), r.value().clone())).collect();
+
+    for (ticker, ts) in tickers {
+        let mut g = ts.mkt.write().await;
+        if g.enter_pause(source, now, retry_at, cfg.cancel_order_on_pause) {
+            warn!(ticker = %ticker, ?source, "trading paused; suspending ticker");
+        }
+        ts.touch(shared);
+    }
+}
+
+/// Clear the pauses the monitor set. Order/WS pauses keep their own `retry_at`: the exchange
+/// being open doesn't mean that ticker's error went away.
+async fn resume_all(shared: &Shared) {
+    let tickers: Vec<_> = shared.tickers.iter().map(|r| (r.key().clone(), r.value().clone())).collect();
+
+    for (ticker, ts) in tickers {
+        let mut g = ts.mkt.write().await;
+        if let Some(p) = g.pause.take_if(|p| p.source == PauseSource::Exchange) {
+            info!(
+                ticker = %ticker,
+                source = ?p.source,
+                paused_s = p.since.elapsed().as_secs(),
+                "trading resumed"
+            );
+            ts.touch(shared);
+        }
+    }
+}
+
+pub async fn run_exchange_monitor(
+    cfg: Config,
+    client: std::sync::Arc<KalshiClient>,
+    shared: Shared,
+    shadows: Vec<ShadowProfile>,
+) -> Result<()> {
+    let mut view = ExchangeView {
+        status: (true, true),
+        maintenance: Vec::new(),
+        seen: HashSet::new(),
+        schedule_polled_at: None,
+    };
+
+    loop {
+        if shared.is_shutting_down() {
+            return Ok(());
+        }
+
+        let schedule_due = view
+            .schedule_polled_at
+            .is_none_or(|t| t.elapsed() >= Duration::from_millis(cfg.exchange_schedule_poll_ms));
+        if schedule_due {
+            view.schedule_polled_at = Some(Instant::now());
+            if let Err(e) = poll_schedule(&client, &mut view).await {
+                warn!("exchange schedule poll failed: {e:?}");
+            }
+            if let Err(e) = poll_announcements(&client, &mut view).await {
+                warn!("exchange announcements poll failed: {e:?}");
+            }
+        }
+
+        match client.get_exchange_status().await {
+            Ok(st) => {
+                let status = (st.exchange_active, st.trading_active);
+                let changed = status != view.status;
+                view.status = status;
+                if st.exchange_active && st.trading_active {
+                    if changed {
+                        info!("exchange status: trading on");
+                    }
+                    resume_all(&shared).await;
+                    for s in &shadows {
+                        resume_all(&s.shared).await;
+                    }
+                } else {
+                    // Polled all through a closure (nights, weekends): log the edge only.
+                    if changed {
+                        info!(
+                            exchange_active = st.exchange_active,
+                            trading_active = st.trading_active,
+                            resume = ?st.exchange_estimated_resume_time,
+                            "exchange status: trading off"
+                        );
+                    }
+                    pause_all(&cfg, &shared, PauseSource::Exchange, None).await;
+                    for s in &shadows {
+                        pause_all(&s.cfg, &s.shared, PauseSource::Exchange, None).await;
+                    }
+                }
+            }
+            Err(e) => warn!("get_exchange_status failed: {e:?}"),
+        }
+
+        let blocked = view.blocked(&cfg, Utc::now().timestamp());
+        if shared.set_trading_allowed(blocked.is_none()) {
+            match blocked {
+                Some(reason) => warn!(reason, "exchange: trading not allowed; pulling quotes, holding rotation"),
+                None => info!("exchange: trading allowed again"),
+            }
+        }
+        for s in &shadows {
+            s.shared.set_trading_allowed(blocked.is_none());
+        }
+
+        sleep(Duration::from_millis(cfg.exchange_status_poll_ms)).await;
+    }
+}
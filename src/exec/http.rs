@@ -1,23 +1,26 @@
 use anyhow::Result;
 use kalshi_rs::KalshiClient;
-use kalshi_rs::portfolio::models::{CreateOrderRequest, CreateOrderResponse};
+use kalshi_rs::portfolio::models::{
+    AmendOrderRequest, AmendOrderResponse, BatchCancelOrdersRequest, CreateOrderRequest,
+    CreateOrderResponse, GetOrdersParams, Order,
+};
 
-use crate::types::{Side, Tif};
+use crate::types::{Action, PlaceParams, Side, Tif, CC_PER_CENT};
 
-/// Place a limit order.
+/// Place a limit order (buy or sell `side`).
 ///
 /// NOTE: CreateOrderRequest does NOT implement Default in kalshi-rs 0.2.1,
 /// so we must construct the struct with all fields.
 pub async fn place(
     client: &KalshiClient,
-    ticker: &str,
-    side: Side,
-    price_cents: u8,
-    qty: u64,
-    tif: Tif,
-    client_order_id: &str,
-    post_only: bool,
+    p: &PlaceParams,
+    self_trade_prevention_type: Option<&str>,
+    cancel_order_on_pause: bool,
 ) -> Result<CreateOrderResponse> {
+    let &PlaceParams {
+        ref ticker, side, action, price_cents, qty, tif, post_only, reduce_only, expiration_ts,
+        buy_max_cost_cc, client_order_id, ..
+    } = p;
     let (yes_price, no_price) = match side {
         Side::Yes => (Some(price_cents as u64), None),
         Side::No => (None, Some(price_cents as u64)),
@@ -26,7 +29,7 @@ pub async fn place(
     let req = CreateOrderRequest {
         ticker: ticker.to_string(),
         side: side.as_str().to_string(),
-        action: "buy".to_string(),
+        action: action.as_str().to_string(),
         count: qty,
 
         client_order_id: Some(client_order_id.to_string()),
@@ -36,21 +39,87 @@ pub async fn place(
 
         yes_price_dollars: None,
         no_price_dollars: None,
-        expiration_ts: None,
+        expiration_ts: expiration_ts.map(|t| t.max(0) as u64),
         time_in_force: Some(tif.as_str().to_string()),
-        buy_max_cost: None,
+        // Exchange takes cents; round down so we never exceed the engine's budget.
+        buy_max_cost: buy_max_cost_cc
+            .filter(|_| action == Action::Buy)
+            .map(|cc| (cc / CC_PER_CENT).max(0) as u64),
 
         post_only: Some(post_only),
-        reduce_only: None,
-        self_trade_prevention_type: None,
+        reduce_only: (action == Action::Sell && reduce_only).then_some(true),
+        self_trade_prevention_type: self_trade_prevention_type.map(str::to_string),
         order_group_id: None,
-        cancel_order_on_pause: None,
+        // Only resting quotes can be left behind by a pause.
+        cancel_order_on_pause: (tif == Tif::Gtc && cancel_order_on_pause).then_some(true),
     };
 
     Ok(client.create_order(&req).await?)
 }
 
+/// Request body for moving a resting order. `count` is the order's new total size
+/// (including anything already filled).
+pub fn amend_request(
+    ticker: &str,
+    side: Side,
+    action: Action,
+    client_order_id: &str,
+    updated_client_order_id: &str,
+    price_cents: u8,
+    count: u64,
+) -> AmendOrderRequest {
+    let (yes_price, no_price) = match side {
+        Side::Yes => (Some(price_cents as u64), None),
+        Side::No => (None, Some(price_cents as u64)),
+    };
+
+    AmendOrderRequest {
+        ticker: ticker.to_string(),
+        side: side.as_str().to_string(),
+        action: action.as_str().to_string(),
+        client_order_id: client_order_id.to_string(),
+        updated_client_order_id: updated_client_order_id.to_string(),
+        yes_price,
+        no_price,
+        yes_price_dollars: None,
+        no_price_dollars: None,
+        count: Some(count),
+    }
+}
+
+pub async fn amend(client: &KalshiClient, order_id: &str, req: &AmendOrderRequest) -> Result<AmendOrderResponse> {
+    Ok(client.amend_order(order_id, req).await?)
+}
+
+/// Does this request error mean trading is paused/halted (rather than a bad order)?
+pub fn is_pause_error(e: &anyhow::Error) -> bool {
+    is_pause_message(&format!("{e:?}"))
+}
+
+pub fn is_pause_message(msg: &str) -> bool {
+    let msg = msg.to_ascii_lowercase();
+    ["paused", "halted", "exchange_closed", "trading_is_not_active"]
+        .iter()
+        .any(|k| msg.contains(k))
+}
+
 pub async fn cancel(client: &KalshiClient, order_id: &str) -> Result<()> {
     client.cancel_order(order_id.to_string()).await?;
     Ok(())
 }
+
+/// All orders the exchange still shows as resting on `ticker`.
+pub async fn list_resting(client: &KalshiClient, ticker: &str) -> Result<Vec<Order>> {
+    let params = GetOrdersParams {
+        ticker: Some(ticker.to_string()),
+        status: Some("resting".to_string()),
+        limit: Some(1000),
+        ..Default::default()
+    };
+    Ok(client.get_orders(&params).await?.orders)
+}
+
+pub async fn batch_cancel(client: &KalshiClient, order_ids: Vec<String>) -> Result<Vec<Order>> {
+    let req = BatchCancelOrdersRequest { order_ids };
+    Ok(client.batch_cancel_orders(&req).await?.orders)
+}
@@ -1,3 +1,4 @@
 pub mod http;
 pub mod task;
-pub mod paper;
\ No newline at end of file
+pub mod paper;
+pub mod telemetry;
\ No newline at end of file
@@ -1,28 +1,171 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use tokio::time::sleep;
+
 use tracing::info;
 
 use crate::state::{Shared};
 use crate::state::orders::OrderStatus;
-use crate::types::{Side, Tif};
+use crate::config::QueueCancelRule;
+use crate::fees::Liquidity;
+use crate::types::{Action, PlaceParams, Side, Tif, WorkingOrder, CC_PER_CENT};
+use crate::state::book::Book;
+use crate::state::ledger::buy_collateral_cc;
 use crate::state::ticker::Market;
+use crate::exec::telemetry::ExecOutcome;
+
+/// FIFO queue model for our resting buys. Call after the book change is applied.
+///
+/// We compare the level's book qty with what we track (ahead + behind):
+/// - growth joined the back of the queue, so it's behind us
+/// - shrinkage is cancels, split by `rule` (trades were already taken off the front by
+///   `paper_on_trade_fill`; this assumes the trade message arrives before its book delta)
+pub fn paper_on_delta_queue(m: &mut Market, side: Side, price: u8, rule: QueueCancelRule) {
+    let level = m.book.level_qty(side, price);
+    for w in m.working_mut(side).iter_mut() {
+        if w.price_cents == price && w.order_id.is_some() {
+            sync_queue(w, level, rule);
+        }
+    }
+}
 
-pub fn paper_on_delta_queue(m: &mut Market, side: Side, price: u8, delta: i64) {
-    if delta >= 0 { 
-        return; 
+/// Resync every working order to the book (after a snapshot replaced it).
+pub fn paper_resync_queues(m: &mut Market, rule: QueueCancelRule) {
+    for side in Side::ALL {
+        let levels: Vec<i64> = m.working(side).iter().map(|w| m.book.level_qty(side, w.price_cents)).collect();
+        for (w, level) in m.working_mut(side).iter_mut().zip(levels) {
+            sync_queue(w, level, rule);
+        }
     }
+}
 
-    if let Some(h) = m.resting_hint_mut(side).as_mut() {
-        if h.price_cents == price && h.order_id.is_some() {
-            // Treat negative delta as “some liquidity at this level disappeared”
-            // and allow it to reduce queue ahead (imperfect but useful).
-            h.queue_ahead = (h.queue_ahead + delta).max(0);
+fn sync_queue(w: &mut WorkingOrder, level: i64, rule: QueueCancelRule) {
+    let tracked = w.queue_ahead + w.queue_behind;
+    if level >= tracked {
+        w.queue_behind += level - tracked;
+        return;
+    }
+
+    let gone = tracked - level;
+    let from_ahead = match rule {
+        QueueCancelRule::Proportional => (gone * w.queue_ahead + tracked / 2) / tracked,
+        QueueCancelRule::BackFirst => gone - w.queue_behind,
+        QueueCancelRule::FrontFirst => gone,
+    };
+    // Whatever one side can't cover comes from the other.
+    let from_ahead = from_ahead.clamp((gone - w.queue_behind).max(0), w.queue_ahead.min(gone));
+    w.queue_ahead -= from_ahead;
+    w.queue_behind -= gone - from_ahead;
+}
+
+/// A match the simulated exchange made. We only learn about it (position, order state)
+/// when it's applied, `paper_fill_delay_ms` later; until then it's in `paper_fills_in_flight`.
+#[derive(Debug, Clone)]
+pub struct PaperFill {
+    pub client_order_id: uuid::Uuid,
+    pub side: Side,
+    pub action: Action,
+    pub price_cents: u8,
+    pub qty: u64,
+}
+
+/// Random delay in `[lo, hi]` ms.
+pub fn sample_delay((lo, hi): (u64, u64)) -> Duration {
+    Duration::from_millis(if hi > lo { rand::random_range(lo..=hi) } else { lo })
+}
+
+/// What the simulated exchange still has open on an order (ignores fills not yet reported to us).
+fn unmatched_qty(m: &Market, client_id: uuid::Uuid) -> Option<u64> {
+    let rec = m.orders.by_client.get(&client_id)?;
+    let in_flight = m.paper_fills_in_flight.get(&client_id).copied().unwrap_or(0);
+    Some(rec.qty.saturating_sub(rec.filled_qty).saturating_sub(in_flight))
+}
+
+fn match_fill(m: &mut Market, fills: &mut Vec<PaperFill>, fill: PaperFill) {
+    *m.paper_fills_in_flight.entry(fill.client_order_id).or_insert(0) += fill.qty;
+    fills.push(fill);
+}
+
+/// Re-sync the paper ledger's reservation for one order after its record changed.
+fn sync_ledger(shared: &Shared, m: &Market, client_id: uuid::Uuid) {
+    if let Some(rec) = m.orders.by_client.get(&client_id) {
+        shared.ledger().sync_order(rec, &m.pos.fee_model);
+    }
+}
+
+/// Report a match to our side: position, order record, working order, paper cash.
+/// Fills still count if a cancel landed while they were in flight.
+pub fn apply_paper_fill(shared: &Shared, ticker: &str, m: &mut Market, f: &PaperFill) {
+    if let Some(q) = m.paper_fills_in_flight.get_mut(&f.client_order_id) {
+        *q = q.saturating_sub(f.qty);
+        if *q == 0 {
+            m.paper_fills_in_flight.remove(&f.client_order_id);
+        }
+    }
+
+    match f.action {
+        Action::Buy => {
+            info!(ticker, side = ?f.side, fill_price = f.price_cents, fill_qty = f.qty, "PAPER maker filled");
+            m.pos.apply_fill(f.side, f.price_cents, f.qty as i64, Liquidity::Maker);
         }
+        Action::Sell => {
+            info!(ticker, side = ?f.side, fill_price = f.price_cents, fill_qty = f.qty, "PAPER resting sell filled");
+            m.pos.apply_sell(f.side, f.price_cents, f.qty as i64, Liquidity::Maker);
+        }
+    }
+    crate::report::log_position(ticker, &m.pos);
+
+    let fee_cc = m.pos.fee_model.fee_cc(Liquidity::Maker, f.price_cents, f.qty);
+    shared.ledger().apply_fill(f.action, f.price_cents, f.qty, fee_cc);
+
+    m.note_first_fill(f.client_order_id, Instant::now());
+    // stop tracking rungs that filled completely
+    if matches!(m.orders.record_fill_by_client(f.client_order_id, f.qty), Some(true)) {
+        m.forget_working_client(f.client_order_id);
     }
+    sync_ledger(shared, m, f.client_order_id);
 }
 
-pub fn paper_on_trade_fill(ticker: &str, m: &mut Market, taker_side: Side, yes_price: u8, no_price: u8, count: i64) {
+/// Apply the matches from one trade now, or after `paper_fill_delay_ms`.
+pub fn deliver_fills(
+    shared: &Shared,
+    ticker: &str,
+    m: &mut Market,
+    fills: Vec<PaperFill>,
+    delay_ms: (u64, u64),
+) {
+    // Orders that expired while matching this trade give their collateral back.
+    shared.ledger().sync_orders(m.orders.by_client.values(), &m.pos.fee_model);
+
+    if fills.is_empty() {
+        return;
+    }
+    let delay = sample_delay(delay_ms);
+    if delay.is_zero() {
+        for f in &fills {
+            apply_paper_fill(shared, ticker, m, f);
+        }
+        return;
+    }
+
+    let shared = shared.clone();
+    let ticker = ticker.to_string();
+    tokio::spawn(async move {
+        sleep(delay).await;
+        let Some(ts) = shared.tickers.get(&ticker) else { return; };
+        let mut g = ts.mkt.write().await;
+        for f in &fills {
+            apply_paper_fill(&shared, &ticker, &mut g, f);
+        }
+        ts.touch(&shared);
+    });
+}
+
+/// Match a public trade against our resting orders. Returns the fills for `deliver_fills`.
+pub fn paper_on_trade_fill(ticker: &str, m: &mut Market, taker_side: Side, yes_price: u8, no_price: u8, count: i64) -> Vec<PaperFill> {
     let fillable = count.max(0) as u64;
     if fillable == 0 { 
-        return; 
+        return Vec::new(); 
     }
 
     // maker side is the opposite side of taker
@@ -32,86 +175,170 @@ pub fn paper_on_trade_fill(ticker: &str, m: &mut Market, taker_side: Side, yes_p
         Side::No  => no_price,
     };
 
-    let (client_id, posted_price, remaining_after_queue_u64) = {
-        let Some(h) = m.resting_hint_mut(maker_side).as_mut() else { return; };
-        // not acked yet
-        if h.order_id.is_none() { 
-            return; 
-        }            
+    // The exchange would already have dropped anything past its expiration_ts.
+    let now_s = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    if m.expire_orders(now_s) > 0 {
+        info!(ticker, "PAPER expired resting orders");
+    }
 
-        // --------- IMPORTANT CHANGE ----------
-        // If the market traded at/through our posted maker price, we should be fill-eligible.
-        //
-        // For a resting BUY at h.price_cents:
-        // - maker_price > h.price_cents  => trade happened above our bid; cannot have hit us
-        // - maker_price == h.price_cents => traded exactly at our level
-        // - maker_price < h.price_cents  => traded through our level (gap/skip); assume we were crossed
-        if maker_price > h.price_cents {
-            return;
-        }
+    let mut fills = paper_fill_resting_sells(m, taker_side, yes_price, no_price, count);
 
-        // If the tape traded below our price, assume our level was swept through;
-        // don't let stale "queue_ahead at our exact price" prevent fills.
-        if maker_price < h.price_cents {
-            h.queue_ahead = 0;
-        }
-        // ------------------------------------
+    // Best-priced rung first: a sell sweeping down the bids reaches our top rung
+    // before the deeper ones. The tape is shared across rungs.
+    let mut rungs: Vec<(usize, u8)> = m.working(maker_side)
+        .iter()
+        .enumerate()
+        .map(|(i, w)| (i, w.price_cents))
+        .collect();
+    rungs.sort_by_key(|&(_, p)| std::cmp::Reverse(p));
 
-        // Consume queue ahead first
-        let mut remaining = fillable as i64;
+    let mut tape = fillable as i64;
 
-        if h.queue_ahead > 0 {
-            let consume = h.queue_ahead.min(remaining);
-            h.queue_ahead -= consume;
-            remaining -= consume;
+    for (i, _) in rungs {
+        if tape <= 0 {
+            break;
         }
 
-        if remaining <= 0 { return; }
+        let (client_id, posted_price) = {
+            let w = &mut m.working_mut(maker_side)[i];
+            // not acked yet
+            if w.order_id.is_none() {
+                continue;
+            }
 
-        (h.client_order_id, h.price_cents, remaining as u64)
-    };
-    let order_remaining = match m.orders.by_client.get(&client_id) {
-        Some(rec) => rec.qty.saturating_sub(rec.filled_qty),
-        None => return,
-    };
-    
-    if order_remaining == 0 { 
-        return; 
-    }
+            // --------- IMPORTANT CHANGE ----------
+            // If the market traded at/through our posted maker price, we should be fill-eligible.
+            //
+            // For a resting BUY at w.price_cents:
+            // - maker_price > w.price_cents  => trade happened above our bid; cannot have hit us
+            // - maker_price == w.price_cents => traded exactly at our level
+            // - maker_price < w.price_cents  => traded through our level (gap/skip); assume we were crossed
+            if maker_price > w.price_cents {
+                continue;
+            }
 
-    let fill_qty = order_remaining.min(remaining_after_queue_u64);
-    if fill_qty == 0 { 
-        return; 
+            // If the tape traded below our price, assume our level was swept through;
+            // don't let stale "queue_ahead at our exact price" prevent fills.
+            if maker_price < w.price_cents {
+                w.queue_ahead = 0;
+            }
+            // ------------------------------------
+
+            // Trades take the front of the queue: consume what's ahead of us first
+            if w.queue_ahead > 0 {
+                let consume = w.queue_ahead.min(tape);
+                w.queue_ahead -= consume;
+                tape -= consume;
+            }
+            if tape <= 0 {
+                break;
+            }
+
+            (w.client_order_id, w.price_cents)
+        };
+
+        let Some(order_remaining) = unmatched_qty(m, client_id) else { continue; };
+        let fill_qty = order_remaining.min(tape as u64);
+        if fill_qty == 0 {
+            continue;
+        }
+        tape -= fill_qty as i64;
+
+        // Option A: fill at OUR posted maker price (conservative)
+        let fill_price = posted_price;
+
+        info!(?maker_side, rung = i, maker_price, fill_price, fill_qty, "PAPER maker matched");
+        match_fill(m, &mut fills, PaperFill {
+            client_order_id: client_id,
+            side: maker_side,
+            action: Action::Buy,
+            price_cents: fill_price,
+            qty: fill_qty,
+        });
     }
 
-    // Option A: fill at OUR posted maker price (conservative)
-    let fill_price = posted_price;
+    fills
+}
 
-    info!(?maker_side, maker_price, fill_price, fill_qty, "PAPER maker filled");
-    m.pos.apply_fill(maker_side, fill_price, fill_qty as i64);
-    crate::report::log_position(ticker, &m.pos);
 
-    let fully = m.orders.record_fill_by_client(client_id, fill_qty);
+/// Levels an IOC would take, best price first, never past `limit`: (price on `side`, qty).
+/// Buying `side` at p lifts the other side's bid at 100 - p; selling hits our side's bids.
+fn ioc_walk(book: &Book, side: Side, action: Action, limit: u8, qty: u64) -> Vec<(u8, u64)> {
+    let prices: Vec<u8> = match action {
+        Action::Buy => (1..=limit.min(99)).collect(),
+        Action::Sell => (limit.max(1)..=99).rev().collect(),
+    };
 
-    if matches!(fully, Some(true)) {
-        // clear resting hint when fully filled
-        *m.resting_hint_mut(maker_side) = None;
+    let mut out = Vec::new();
+    let mut left = qty;
+    for p in prices {
+        if left == 0 {
+            break;
+        }
+        let avail = match action {
+            Action::Buy => book.level_qty(side.other(), 100 - p),
+            Action::Sell => book.level_qty(side, p),
+        };
+        if avail <= 0 {
+            continue;
+        }
+        let q = left.min(avail as u64);
+        out.push((p, q));
+        left -= q;
     }
+    out
 }
 
+/// Resting (GTC) sells: a taker BUYING our side above our ask must have gone through us.
+/// We don't model a queue on the ask side, so a trade exactly at our price doesn't fill us.
+fn paper_fill_resting_sells(m: &mut Market, taker_side: Side, yes_price: u8, no_price: u8, count: i64) -> Vec<PaperFill> {
+    let mut fills = Vec::new();
+    let mut tape = count.max(0) as u64;
+    let trade_price = match taker_side {
+        Side::Yes => yes_price,
+        Side::No => no_price,
+    };
 
-pub async fn paper_place(
-    shared: &Shared,
-    ticker: &str,
-    side: Side,
-    price_cents: u8,
-    qty: u64,
-    tif: Tif,
-    post_only: bool,
-    client_order_id: uuid::Uuid,
-    reject_postonly_cross: bool,
-) {
-    let Some(ts) = shared.tickers.get(ticker) else { return; };
+    let candidates: Vec<(uuid::Uuid, u8)> = m.orders.by_client
+        .values()
+        .filter(|r| r.action == Action::Sell && r.side == taker_side && r.status == OrderStatus::Resting)
+        .filter(|r| trade_price > r.price_cents)
+        .map(|r| (r.client_order_id, r.price_cents))
+        .collect();
+
+    for (client_id, price) in candidates {
+        if tape == 0 {
+            break;
+        }
+        let remaining = unmatched_qty(m, client_id).unwrap_or(0);
+        let fill_qty = remaining.min(tape);
+        if fill_qty == 0 {
+            continue;
+        }
+        tape -= fill_qty;
+
+        info!(side = ?taker_side, trade_price, fill_price = price, fill_qty, "PAPER resting sell matched");
+        match_fill(m, &mut fills, PaperFill {
+            client_order_id: client_id,
+            side: taker_side,
+            action: Action::Sell,
+            price_cents: price,
+            qty: fill_qty,
+        });
+    }
+    fills
+}
+
+pub async fn paper_place(shared: &Shared, p: &PlaceParams, reject_postonly_cross: bool) -> ExecOutcome {
+    let &PlaceParams {
+        ref ticker, side, action, price_cents, qty, tif, post_only, reduce_only, buy_max_cost_cc,
+        client_order_id, ..
+    } = p;
+    let ticker = ticker.as_str();
+    let Some(ts) = shared.tickers.get(ticker) else { return ExecOutcome::Other; };
     let mut g = ts.mkt.write().await;
 
     // synthetic exchange order id
@@ -119,79 +346,288 @@ pub async fn paper_place(
     g.orders.link_order_id(client_order_id, &order_id);
 
     // Post-only reject if it would cross *right now*
-    if post_only && reject_postonly_cross && g.book.crosses_ask(side, price_cents) {
-        info!(ticker, ?side, price_cents, qty, "PAPER reject post_only would-cross");
+    let would_cross = match action {
+        Action::Buy => g.book.crosses_ask(side, price_cents),
+        Action::Sell => g.book.crosses_bid(side, price_cents),
+    };
+    if post_only && reject_postonly_cross && would_cross {
+        info!(ticker, ?side, %action, price_cents, qty, "PAPER reject post_only would-cross");
         g.orders.set_status_by_client(client_order_id, OrderStatus::Rejected);
 
-        // Clear hint if we set one for this client_order_id
-        if let Some(h) = g.resting_hint(side).as_ref() {
-            if h.client_order_id == client_order_id {
-                *g.resting_hint_mut(side) = None;
-            }
-        }
+        // Forget the working order if we tracked one for this client_order_id
+        g.forget_working_client(client_order_id);
 
         ts.touch(&shared);
-        return;
+        return ExecOutcome::PostOnlyReject;
     }
 
-    match tif {
-        Tif::Ioc => {
-            // IOC: fill if limit >= implied ask
-            let Some(ask) = g.book.implied_ask(side) else {
-                info!(ticker, ?side, price_cents, "PAPER ioc reject no-ask");
-                g.orders.set_status_by_client(client_order_id, OrderStatus::Rejected);
-                ts.touch(&shared);
-                return;
+    // Buys need the collateral for the whole order at its limit, like the exchange checks.
+    if action == Action::Buy {
+        let need_cc = buy_collateral_cc(&g.pos.fee_model, price_cents, qty);
+        let available_cc = shared.ledger().available_cc();
+        if need_cc > available_cc {
+            info!(ticker, ?side, price_cents, qty, need_cc, available_cc, "PAPER reject insufficient balance");
+            g.orders.set_status_by_client(client_order_id, OrderStatus::Rejected);
+            g.forget_working_client(client_order_id);
+            ts.touch(shared);
+            return ExecOutcome::InsufficientBalance;
+        }
+    }
+
+    match (tif, action) {
+        (Tif::Ioc, _) => {
+            let held = match side {
+                Side::Yes => g.pos.yes_qty,
+                Side::No => g.pos.no_qty,
+            }.max(0) as u64;
+            let want = match action {
+                Action::Buy => qty,
+                // reduce_only: never sell more than we hold
+                Action::Sell if reduce_only => qty.min(held),
+                Action::Sell => qty,
             };
 
-            if ask <= price_cents {
-                let fill_qty = qty;
-                info!(ticker, ?side, limit=price_cents, fill_price=ask, fill_qty, "PAPER ioc filled");
-                g.pos.apply_fill(side, ask, fill_qty as i64);
-                let _ = g.orders.record_fill_by_client(client_order_id, fill_qty);
-                crate::report::log_position(ticker, &g.pos);
-            } else {
-                info!(ticker, ?side, limit=price_cents, ask, "PAPER ioc not-filled reject");
+            let levels = ioc_walk(&g.book, side, action, price_cents, want);
+            let filled: u64 = levels.iter().map(|&(_, q)| q).sum();
+            let cost_cc: i64 = levels.iter().map(|&(p, q)| p as i64 * CC_PER_CENT * q as i64).sum();
+
+            // `buy_max_cost` makes a buy fill-or-kill within that spend.
+            let killed = action == Action::Buy
+                && buy_max_cost_cc.is_some_and(|max| filled < qty || cost_cc > max);
+            if filled == 0 || killed {
+                info!(ticker, ?side, %action, limit=price_cents, qty, held, ?buy_max_cost_cc, "PAPER ioc not-filled reject");
                 g.orders.set_status_by_client(client_order_id, OrderStatus::Rejected);
+                ts.touch(shared);
+                return ExecOutcome::NotFilled;
             }
 
-            ts.touch(&shared);
+            let mut realized = 0;
+            let liquidity = Liquidity::Taker;
+            for &(p, q) in &levels {
+                let fee_cc = g.pos.fee_model.fee_cc(liquidity, p, q);
+                shared.ledger().apply_fill(action, p, q, fee_cc);
+                match action {
+                    Action::Buy => {
+                        g.book.take_liquidity(side.other(), 100 - p, q);
+                        g.pos.apply_fill(side, p, q as i64, liquidity);
+                    }
+                    Action::Sell => {
+                        g.book.take_liquidity(side, p, q);
+                        realized += g.pos.apply_sell(side, p, q as i64, liquidity);
+                    }
+                }
+            }
+            g.note_first_fill(client_order_id, Instant::now());
+            let _ = g.orders.record_fill_by_client(client_order_id, filled);
+            // IOC: whatever the book couldn't fill is cancelled.
+            if filled < qty {
+                g.orders.set_status_by_client(client_order_id, OrderStatus::Canceled);
+            }
+
+            let avg_price_cents = cost_cc as f64 / (filled as f64 * CC_PER_CENT as f64);
+            info!(
+                ticker, ?side, %action, limit=price_cents, qty, fill_qty = filled,
+                avg_price_cents, levels = levels.len(), "PAPER ioc filled"
+            );
+            if action == Action::Sell {
+                info!(ticker, realized_cc = realized, "PAPER realized");
+            }
+            crate::report::log_position(ticker, &g.pos);
+
+            ts.touch(shared);
         }
 
-        Tif::Gtc => {
+        (Tif::Gtc, _) => {
             // GTC: accept as resting
-            info!(ticker, ?side, price_cents, qty, post_only, order_id=%order_id, "PAPER resting ack");
+            info!(ticker, ?side, %action, price_cents, qty, post_only, order_id=%order_id, "PAPER resting ack");
 
             g.orders.set_status_by_client(client_order_id, OrderStatus::Resting);
-
-            // Fill in hint order_id so cancels work
-            if let Some(h) = g.resting_hint_mut(side).as_mut() {
-                if h.client_order_id == client_order_id {
-                    h.order_id = Some(order_id);
-                }
+            sync_ledger(shared, &g, client_order_id);
+
+            // Fill in the working order's id so cancels work. The order reaches the
+            // simulated book only now, so it joins the back of the queue as of the ack.
+            let level = g.book.level_qty(side, price_cents);
+            if let Some(w) = g.working_by_client_mut(side, client_order_id) {
+                w.order_id = Some(order_id);
+                w.queue_ahead = level;
+                w.queue_behind = 0;
             }
 
             ts.touch(&shared);
         }
     }
+
+    ExecOutcome::Accepted
+}
+
+/// Amend a resting paper order. Same rules as the exchange: the old order must still be
+/// resting, and a post-only amend that would cross is rejected (old order stays).
+pub async fn paper_amend(
+    shared: &Shared,
+    ticker: &str,
+    side: Side,
+    client_order_id: uuid::Uuid,
+    updated_client_order_id: uuid::Uuid,
+    price_cents: u8,
+    reject_postonly_cross: bool,
+) -> ExecOutcome {
+    let Some(ts) = shared.tickers.get(ticker) else { return ExecOutcome::Other; };
+    let mut g = ts.mkt.write().await;
+
+    let live = g.orders.by_client
+        .get(&client_order_id)
+        .is_some_and(|r| r.status == OrderStatus::Resting);
+    let crosses = reject_postonly_cross && g.book.crosses_ask(side, price_cents);
+    if !live || crosses {
+        info!(ticker, ?side, price_cents, live, "PAPER amend reject");
+        g.orders.set_status_by_client(updated_client_order_id, OrderStatus::Rejected);
+        ts.touch(shared);
+        return if live { ExecOutcome::PostOnlyReject } else { ExecOutcome::Other };
+    }
+
+    // The amended order only needs collateral beyond what the old one already holds.
+    let need_cc = g.orders.by_client
+        .get(&updated_client_order_id)
+        .map_or(0, |r| buy_collateral_cc(&g.pos.fee_model, price_cents, r.qty));
+    let funded = {
+        let ledger = shared.ledger();
+        ledger.can_fund(need_cc, ledger.held_by(client_order_id))
+    };
+    if !funded {
+        info!(ticker, ?side, price_cents, need_cc, "PAPER amend reject insufficient balance");
+        g.orders.set_status_by_client(updated_client_order_id, OrderStatus::Rejected);
+        ts.touch(shared);
+        return ExecOutcome::InsufficientBalance;
+    }
+
+    let order_id = format!("paper-{}", uuid::Uuid::new_v4());
+    g.apply_amend_ack(side, client_order_id, updated_client_order_id, &order_id, OrderStatus::Resting, Instant::now());
+    sync_ledger(shared, &g, client_order_id);
+    sync_ledger(shared, &g, updated_client_order_id);
+
+    info!(ticker, ?side, price_cents, order_id = %order_id, "PAPER amend ack");
+    ts.touch(shared);
+    ExecOutcome::Accepted
 }
 
 pub async fn paper_cancel(shared: &Shared, ticker: &str, order_id: &str) {
     let Some(ts) = shared.tickers.get(ticker) else { return; };
     let mut g = ts.mkt.write().await;
 
-    // If already filled, do nothing (realistic race behavior)
-    // (status lookup optional; we’ll just attempt cancel)
-    g.orders.set_status_by_order(order_id, OrderStatus::Canceled);
-
-    for side in Side::ALL {
-        if let Some(h) = g.resting_hint(side).as_ref() {
-            if h.order_id.as_deref() == Some(order_id) {
-                *g.resting_hint_mut(side) = None;
-            }
-        }
+    // Already fully matched: nothing left to cancel (fills in flight still get applied).
+    let open = g.orders.by_order
+        .get(order_id)
+        .and_then(|c| unmatched_qty(&g, *c))
+        .is_some_and(|q| q > 0);
+    if open {
+        g.orders.set_status_by_order(order_id, OrderStatus::Canceled);
+    }
+    if let Some(client_id) = g.orders.by_order.get(order_id).copied() {
+        sync_ledger(shared, &g, client_id);
     }
 
+    g.forget_working_order(order_id);
+
     info!(ticker, order_id, "PAPER cancel ack");
     ts.touch(&shared);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::orders::OrderRec;
+
+    fn queued(ahead: i64, behind: i64) -> WorkingOrder {
+        WorkingOrder {
+            side: Side::Yes,
+            rung: 0,
+            price_cents: 40,
+            created_at: Instant::now(),
+            cancel_requested_at: None,
+            client_order_id: uuid::Uuid::new_v4(),
+            order_id: Some("paper-1".to_string()),
+            queue_ahead: ahead,
+            queue_behind: behind,
+        }
+    }
+
+    /// An acked resting buy of `qty` on Yes at 40c, with `ahead` queued in front of it.
+    fn resting(m: &mut Market, qty: u64, ahead: i64) -> uuid::Uuid {
+        let w = queued(ahead, 0);
+        let id = w.client_order_id;
+        m.orders.insert_pending(OrderRec {
+            ticker: "T".to_string(),
+            side: Side::Yes,
+            action: Action::Buy,
+            price_cents: 40,
+            qty,
+            tif: Tif::Gtc,
+            post_only: true,
+            order_id: w.order_id.clone(),
+            client_order_id: id,
+            status: OrderStatus::Resting,
+            created_at: Instant::now(),
+            expiration_ts: None,
+            filled_qty: 0,
+        });
+        m.working_mut(Side::Yes).push(w);
+        id
+    }
+
+    fn after(rule: QueueCancelRule, ahead: i64, behind: i64, level: i64) -> (i64, i64) {
+        let mut w = queued(ahead, behind);
+        sync_queue(&mut w, level, rule);
+        (w.queue_ahead, w.queue_behind)
+    }
+
+    #[test]
+    fn growth_queues_behind_us() {
+        for rule in [QueueCancelRule::Proportional, QueueCancelRule::BackFirst, QueueCancelRule::FrontFirst] {
+            assert_eq!(after(rule, 10, 0, 15), (10, 5));
+        }
+    }
+
+    #[test]
+    fn cancels_split_by_rule() {
+        // 6 ahead, 4 behind, 5 cancelled.
+        assert_eq!(after(QueueCancelRule::Proportional, 6, 4, 5), (3, 2));
+        assert_eq!(after(QueueCancelRule::BackFirst, 6, 4, 5), (5, 0));
+        assert_eq!(after(QueueCancelRule::FrontFirst, 6, 4, 5), (1, 4));
+
+        // More cancelled than one side holds: the other side covers the rest.
+        assert_eq!(after(QueueCancelRule::BackFirst, 6, 4, 2), (2, 0));
+        assert_eq!(after(QueueCancelRule::FrontFirst, 6, 4, 2), (0, 2));
+        assert_eq!(after(QueueCancelRule::Proportional, 6, 4, 0), (0, 0));
+    }
+
+    #[test]
+    fn trades_eat_the_queue_ahead_before_filling_us() {
+        let mut m = Market::new();
+        let id = resting(&mut m, 5, 10);
+
+        // A No taker at 60 hits Yes bids at 40: 8 of the 10 ahead of us.
+        assert!(paper_on_trade_fill("T", &mut m, Side::No, 40, 60, 8).is_empty());
+        assert_eq!(m.working(Side::Yes)[0].queue_ahead, 2);
+
+        let fills = paper_on_trade_fill("T", &mut m, Side::No, 40, 60, 5);
+        assert_eq!(fills.len(), 1);
+        assert_eq!((fills[0].client_order_id, fills[0].price_cents, fills[0].qty), (id, 40, 3));
+        assert_eq!(m.working(Side::Yes)[0].queue_ahead, 0);
+
+        // Only 2 left unmatched, whatever the tape says.
+        let fills = paper_on_trade_fill("T", &mut m, Side::No, 40, 60, 10);
+        assert_eq!(fills[0].qty, 2);
+    }
+
+    #[test]
+    fn trade_through_our_price_skips_the_queue() {
+        let mut m = Market::new();
+        resting(&mut m, 5, 100);
+
+        // Above our bid: never reaches us.
+        assert!(paper_on_trade_fill("T", &mut m, Side::No, 41, 59, 50).is_empty());
+        // Below it: our level was swept, fill at our price.
+        let fills = paper_on_trade_fill("T", &mut m, Side::No, 39, 61, 4);
+        assert_eq!((fills[0].price_cents, fills[0].qty), (40, 4));
+    }
+}
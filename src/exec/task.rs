@@ -1,26 +1,88 @@
 use anyhow::Result;
 use tokio::sync::mpsc;
+use tokio::time::sleep;
 use tracing::{info, warn};
 
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use kalshi_rs::KalshiClient;
 
 use crate::exec::{http, paper};
+use crate::exec::telemetry::{CmdKind, ExecOutcome};
 use crate::state::orders::OrderStatus;
 use crate::state::Shared;
-use crate::types::{ExecCommand, Side, Tif};
+use crate::state::ticker::Market;
+use crate::types::{Action, ExecCommand, PauseSource, PlaceParams, Tif};
 use crate::config::Config;
 
 fn kalshi_status_to_local(status: &str) -> OrderStatus {
     match status {
         "resting" => OrderStatus::Resting,
         "canceled" => OrderStatus::Canceled,
-        "filled" => OrderStatus::Filled,
+        "filled" | "executed" => OrderStatus::Filled,
         _ => OrderStatus::Resting, // conservatitve default
     }
 }
 
+/// A pause/halt error: suspend the ticker instead of letting the engine retry into it.
+fn pause_on_error(cfg: &Config, g: &mut Market, ticker: &str, e: &anyhow::Error) {
+    if !http::is_pause_error(e) {
+        return;
+    }
+    let now = Instant::now();
+    let retry_at = now + Duration::from_millis(cfg.pause_retry_ms);
+    if g.enter_pause(PauseSource::OrderError, now, Some(retry_at), cfg.cancel_order_on_pause) {
+        warn!(ticker = %ticker, "trading paused (order error); suspending ticker");
+    }
+}
+
+/// Insufficient balance: hold off new buys on the ticker and refresh the balance now,
+/// rather than letting the engine retry into the same reject.
+fn backoff_on_balance(cfg: &Config, shared: &Shared, g: &mut Market, ticker: &str, outcome: ExecOutcome) {
+    if outcome != ExecOutcome::InsufficientBalance {
+        return;
+    }
+    warn!(ticker = %ticker, backoff_ms = cfg.sizing_reject_backoff_ms, "insufficient balance; holding new buys");
+    g.buy_backoff_until = Some(Instant::now() + Duration::from_millis(cfg.sizing_reject_backoff_ms));
+    shared.capital_refresh.notify_one();
+}
+
+/// Sample decision -> response for a command whose outcome is known.
+async fn record_exec(
+    cfg: &Config,
+    shared: &Shared,
+    ticker: &str,
+    kind: CmdKind,
+    decided_at: Instant,
+    outcome: ExecOutcome,
+) {
+    let latency = decided_at.elapsed();
+    if let Some(ts) = shared.tickers.get(ticker) {
+        let mut g = ts.mkt.write().await;
+        g.exec_stats.record_response(kind, latency, outcome);
+        backoff_on_balance(cfg, shared, &mut g, ticker, outcome);
+    }
+}
+
+/// Shutdown: undo a place that will never reach the exchange.
+async fn drop_place(shared: &Shared, p: &PlaceParams) {
+    info!(ticker = %p.ticker, side = ?p.side, price_cents = p.price_cents, "shutdown: dropping place");
+    if let Some(ts) = shared.tickers.get(&p.ticker) {
+        let mut g = ts.mkt.write().await;
+        g.orders.set_status_by_client(p.client_order_id, OrderStatus::Rejected);
+        g.forget_working_client(p.client_order_id);
+    }
+}
+
+/// Shutdown: drop an amend. The order it would have replaced stays as it was.
+async fn drop_amend(shared: &Shared, ticker: &str, updated_client_order_id: uuid::Uuid) {
+    if let Some(ts) = shared.tickers.get(ticker) {
+        let mut g = ts.mkt.write().await;
+        g.orders.set_status_by_client(updated_client_order_id, OrderStatus::Rejected);
+    }
+}
+
 pub async fn run_exec(
     cfg: Config,
     client: Arc<KalshiClient>,
@@ -29,35 +91,42 @@ pub async fn run_exec(
 ) -> Result<()> {
     while let Some(cmd) = rx.recv().await {
         match cmd {
-            ExecCommand::PlaceOrder {
-                ticker,
-                side,
-                price_cents,
-                qty,
-                tif,
-                post_only,
-                client_order_id,
-            } => {
+            ExecCommand::Place(p) => {
+                let PlaceParams { side, action, price_cents, tif, post_only, client_order_id, decided_at, .. } = p;
+                let ticker = p.ticker.clone();
+                // Anything the engine queued before shutdown must not reach the exchange.
+                if shared.is_shutting_down() {
+                    drop_place(&shared, &p).await;
+                    continue;
+                }
 
                 if cfg.exec_mode.is_paper() {
-                    paper::paper_place(
-                        &shared, &ticker, side, price_cents, qty, tif, post_only,
-                        client_order_id, cfg.paper_reject_postonly_cross
-                    ).await;
+                    // The simulated exchange sees the order only after the ack delay.
+                    let delay = paper::sample_delay(cfg.paper_ack_delay_ms);
+                    let shared = shared.clone();
+                    let cfg = cfg.clone();
+                    let reject_cross = cfg.paper_reject_postonly_cross;
+                    tokio::spawn(async move {
+                        sleep(delay).await;
+                        // Shutdown began while it was in flight: it never reaches the sim.
+                        if shared.is_shutting_down() {
+                            drop_place(&shared, &p).await;
+                            return;
+                        }
+                        let outcome = paper::paper_place(&shared, &p, reject_cross).await;
+                        record_exec(&cfg, &shared, &ticker, CmdKind::Place, decided_at, outcome).await;
+                    });
                     continue;
                 }
 
                 let res = http::place(
                     &client,
-                    &ticker,
-                    side,
-                    price_cents,
-                    qty,
-                    tif,
-                    &client_order_id.to_string(),
-                    post_only,
+                    &p,
+                    cfg.self_trade_prevention_type.as_deref(),
+                    cfg.cancel_order_on_pause,
                 )
                 .await;
+                let latency = decided_at.elapsed();
 
                 match res {
                     Ok(resp) => {
@@ -66,12 +135,13 @@ pub async fn run_exec(
                         let status = resp.order.status.clone();
 
                         info!(
-                            "placed order side={:?} tif={:?} post_only={} price={} id={} status={}",
-                            side, tif, post_only, price_cents, order_id, status
+                            "placed order side={:?} action={} tif={:?} post_only={} price={} id={} status={}",
+                            side, action, tif, post_only, price_cents, order_id, status
                         );
 
                         if let Some(ts) = shared.tickers.get(&ticker) {
                             let mut g = ts.mkt.write().await;
+                            g.exec_stats.record_response(CmdKind::Place, latency, ExecOutcome::Accepted);
 
                             // Link exchange order_id to our client_order_id
                             g.orders.link_order_id(client_order_id, &order_id);
@@ -80,16 +150,17 @@ pub async fn run_exec(
                             let st = kalshi_status_to_local(status.as_str());
                             g.orders.set_status_by_client(client_order_id, st);
 
-                            // If this was meant to be a resting order, fill in the hint's order_id.
-                            if tif == Tif::Gtc && post_only {
-                                if let Some(h) = g.resting_hint_mut(side).as_mut() {
-                                    if h.client_order_id == client_order_id {
-                                        h.order_id = Some(order_id.clone());
-                                    }
-                                }
+                            // If this was meant to be a resting quote, fill in the working order's id.
+                            // (Working orders only track our buy quotes.)
+                            if tif == Tif::Gtc
+                                && post_only
+                                && action == Action::Buy
+                                && let Some(w) = g.working_by_client_mut(side, client_order_id)
+                            {
+                                w.order_id = Some(order_id.clone());
                             }
 
-                            // If it was IOC, we don’t keep any resting hint.
+                            // If it was IOC, we don’t keep any working order.
                             // Fills will come through websocket (fill channel).
                             ts.touch(&shared);
                         }
@@ -98,15 +169,14 @@ pub async fn run_exec(
                         warn!("place failed: {e:?}");
                         if let Some(ts) = shared.tickers.get(&ticker) {
                             let mut g = ts.mkt.write().await;
+                            let outcome = ExecOutcome::from_error(&e);
+                            g.exec_stats.record_response(CmdKind::Place, latency, outcome);
+                            backoff_on_balance(&cfg, &shared, &mut g, &ticker, outcome);
                             g.orders.set_status_by_client(client_order_id, OrderStatus::Rejected);
 
-                            // If we thought this was resting, clear the hint so engine can try again.
-                            if g.resting_hint(side)
-                                .as_ref()
-                                .is_some_and(|h| h.client_order_id == client_order_id)
-                                {
-                                    *g.resting_hint_mut(side) = None;
-                                }
+                            // If we thought this was resting, forget it so engine can try again.
+                            g.forget_working_client(client_order_id);
+                            pause_on_error(&cfg, &mut g, &ticker, &e);
 
                             ts.touch(&shared);
                         }
@@ -114,39 +184,132 @@ pub async fn run_exec(
                 }
             }
 
-            ExecCommand::CancelOrder { ticker, order_id } => {
+            ExecCommand::Amend {
+                ticker,
+                side,
+                action,
+                order_id,
+                client_order_id,
+                updated_client_order_id,
+                price_cents,
+                count,
+                decided_at,
+            } => {
+                if shared.is_shutting_down() {
+                    drop_amend(&shared, &ticker, updated_client_order_id).await;
+                    continue;
+                }
+
                 if cfg.exec_mode.is_paper() {
-                    paper::paper_cancel(&shared, &ticker, &order_id).await;
+                    let delay = paper::sample_delay(cfg.paper_ack_delay_ms);
+                    let shared = shared.clone();
+                    let cfg = cfg.clone();
+                    let reject_cross = cfg.paper_reject_postonly_cross;
+                    tokio::spawn(async move {
+                        sleep(delay).await;
+                        if shared.is_shutting_down() {
+                            drop_amend(&shared, &ticker, updated_client_order_id).await;
+                            return;
+                        }
+                        let outcome = paper::paper_amend(
+                            &shared, &ticker, side, client_order_id, updated_client_order_id,
+                            price_cents, reject_cross
+                        ).await;
+                        record_exec(&cfg, &shared, &ticker, CmdKind::Amend, decided_at, outcome).await;
+                    });
+                    continue;
+                }
+
+                let req = http::amend_request(
+                    &ticker,
+                    side,
+                    action,
+                    &client_order_id.to_string(),
+                    &updated_client_order_id.to_string(),
+                    price_cents,
+                    count,
+                );
+                let res = http::amend(&client, &order_id, &req).await;
+                let latency = decided_at.elapsed();
+
+                let Some(ts) = shared.tickers.get(&ticker) else { continue; };
+                let mut g = ts.mkt.write().await;
+                match res {
+                    Ok(resp) => {
+                        g.exec_stats.record_response(CmdKind::Amend, latency, ExecOutcome::Accepted);
+                        let new_id = resp.order.order_id.clone();
+                        let status = resp.order.status.clone();
+                        info!(
+                            "amended order side={:?} price={} count={} old_id={} id={} status={}",
+                            side, price_cents, count, order_id, new_id, status
+                        );
+                        g.apply_amend_ack(
+                            side,
+                            client_order_id,
+                            updated_client_order_id,
+                            &new_id,
+                            kalshi_status_to_local(status.as_str()),
+                            Instant::now(),
+                        );
+                    }
+                    Err(e) => {
+                        // Old order is untouched; the order manager retries after cancel_retry_ms.
+                        warn!("amend failed: {e:?}");
+                        let outcome = ExecOutcome::from_error(&e);
+                        g.exec_stats.record_response(CmdKind::Amend, latency, outcome);
+                        backoff_on_balance(&cfg, &shared, &mut g, &ticker, outcome);
+                        g.orders.set_status_by_client(updated_client_order_id, OrderStatus::Rejected);
+                        pause_on_error(&cfg, &mut g, &ticker, &e);
+                    }
+                }
+                ts.touch(&shared);
+            }
+
+            ExecCommand::Cancel { ticker, order_id, decided_at } => {
+                if cfg.exec_mode.is_paper() {
+                    // The order stays live (and fillable) until the cancel lands.
+                    let delay = paper::sample_delay(cfg.paper_cancel_delay_ms);
+                    let shared = shared.clone();
+                    let cfg = cfg.clone();
+                    tokio::spawn(async move {
+                        sleep(delay).await;
+                        // Shutdown's own sweep cancels every paper order.
+                        if shared.is_shutting_down() {
+                            return;
+                        }
+                        paper::paper_cancel(&shared, &ticker, &order_id).await;
+                        record_exec(&cfg, &shared, &ticker, CmdKind::Cancel, decided_at, ExecOutcome::Accepted).await;
+                    });
                     continue;
                 }
 
                 let res = http::cancel(&client, &order_id).await;
+                let latency = decided_at.elapsed();
                 match res {
                     Ok(_) => {
                         info!("canceled order_id={}", order_id);
 
                         if let Some(ts) = shared.tickers.get(&ticker) {
                             let mut g = ts.mkt.write().await;
+                            g.exec_stats.record_response(CmdKind::Cancel, latency, ExecOutcome::Accepted);
 
                             g.orders.set_status_by_order(&order_id, OrderStatus::Canceled);
 
-                            // Clear any resting hint that matches this order_id.
-                            for side in Side::ALL {
-                                if g.resting_hint(side)
-                                    .as_ref()
-                                    .is_some_and(|h| h.order_id.as_deref() == Some(order_id.as_str()))
-                                    {
-                                        *g.resting_hint_mut(side) = None;
-                                    }
-                            }
+                            // Stop tracking the working order with this order_id.
+                            g.forget_working_order(&order_id);
 
                             ts.touch(&shared);
                         }
                     }
                     Err(e) => {
                         warn!("cancel failed: {e:?}");
-                        // On cancel failure, we just leave hint intact;
+                        // On cancel failure, we just leave the working order intact;
                         // engine will retry after cfg.cancel_retry_ms due to cancel_requested_at timestamp.
+                        if let Some(ts) = shared.tickers.get(&ticker) {
+                            let mut g = ts.mkt.write().await;
+                            g.exec_stats.record_response(CmdKind::Cancel, latency, ExecOutcome::from_error(&e));
+                            pause_on_error(&cfg, &mut g, &ticker, &e);
+                        }
                     }
                 }
             }
@@ -0,0 +1,223 @@
+//! Execution latency/outcome telemetry.
+//!
+//! Each ticker keeps an `ExecStats` in its `Market`: per command kind, a rolling window of
+//! decision -> exchange response latencies, decision -> first fill latencies, and outcome counts.
+//! `run_telemetry_reporter` logs percentiles per ticker and across tickers on an interval.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use tokio::time::sleep;
+use tracing::info;
+
+use crate::config::Config;
+use crate::state::Shared;
+
+// Samples kept per series; older ones roll off.
+const ROLLING_SAMPLES: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmdKind {
+    Place,
+    Amend,
+    Cancel,
+}
+
+impl CmdKind {
+    pub const ALL: [CmdKind; 3] = [CmdKind::Place, CmdKind::Amend, CmdKind::Cancel];
+
+    fn idx(self) -> usize {
+        self as usize
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CmdKind::Place => "place",
+            CmdKind::Amend => "amend",
+            CmdKind::Cancel => "cancel",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecOutcome {
+    Accepted,
+    PostOnlyReject,
+    InsufficientBalance,
+    RateLimited,
+    // An IOC that traded nothing (empty book at its limit, or killed by `buy_max_cost`).
+    NotFilled,
+    Other,
+}
+
+impl ExecOutcome {
+    const COUNT: usize = 6;
+
+    fn idx(self) -> usize {
+        self as usize
+    }
+
+    /// Bucket a failed request by its error text (`HTTP {status}: {body}` for API errors).
+    pub fn from_error(e: &anyhow::Error) -> Self {
+        let msg = format!("{e:?}").to_ascii_lowercase();
+        if msg.contains("post_only") || msg.contains("post only") || msg.contains("post-only") {
+            ExecOutcome::PostOnlyReject
+        } else if msg.contains("insufficient_balance") || msg.contains("insufficient balance") {
+            ExecOutcome::InsufficientBalance
+        } else if msg.contains("http 429") || msg.contains("rate limit") || msg.contains("too_many_requests") {
+            ExecOutcome::RateLimited
+        } else {
+            ExecOutcome::Other
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Series {
+    response_us: VecDeque<u64>,
+    first_fill_us: VecDeque<u64>,
+    outcomes: [u64; ExecOutcome::COUNT],
+}
+
+fn push_sample(q: &mut VecDeque<u64>, d: Duration) {
+    if q.len() == ROLLING_SAMPLES {
+        q.pop_front();
+    }
+    q.push_back(d.as_micros() as u64);
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExecStats {
+    series: [Series; 3],
+}
+
+impl ExecStats {
+    /// Decision -> exchange response, with how it went.
+    pub fn record_response(&mut self, kind: CmdKind, latency: Duration, outcome: ExecOutcome) {
+        let s = &mut self.series[kind.idx()];
+        push_sample(&mut s.response_us, latency);
+        s.outcomes[outcome.idx()] += 1;
+    }
+
+    /// Decision -> first fill on the order that decision created.
+    pub fn record_first_fill(&mut self, kind: CmdKind, latency: Duration) {
+        push_sample(&mut self.series[kind.idx()].first_fill_us, latency);
+    }
+
+    /// Fold another ticker's samples into this one (for cross-ticker summaries).
+    pub fn merge(&mut self, other: &ExecStats) {
+        for (a, b) in self.series.iter_mut().zip(other.series.iter()) {
+            a.response_us.extend(b.response_us.iter().copied());
+            a.first_fill_us.extend(b.first_fill_us.iter().copied());
+            for (x, y) in a.outcomes.iter_mut().zip(b.outcomes.iter()) {
+                *x += y;
+            }
+        }
+    }
+
+    pub fn summary(&self, kind: CmdKind) -> Option<ExecSummary> {
+        let s = &self.series[kind.idx()];
+        let total: u64 = s.outcomes.iter().sum();
+        if total == 0 && s.first_fill_us.is_empty() {
+            return None;
+        }
+        Some(ExecSummary {
+            kind,
+            response_ms: Percentiles::of(&s.response_us),
+            first_fill_ms: Percentiles::of(&s.first_fill_us),
+            accepted: s.outcomes[ExecOutcome::Accepted.idx()],
+            post_only_reject: s.outcomes[ExecOutcome::PostOnlyReject.idx()],
+            insufficient_balance: s.outcomes[ExecOutcome::InsufficientBalance.idx()],
+            rate_limited: s.outcomes[ExecOutcome::RateLimited.idx()],
+            not_filled: s.outcomes[ExecOutcome::NotFilled.idx()],
+            other: s.outcomes[ExecOutcome::Other.idx()],
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Percentiles {
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl Percentiles {
+    // Nearest-rank percentiles in milliseconds.
+    fn of(samples_us: &VecDeque<u64>) -> Option<Self> {
+        if samples_us.is_empty() {
+            return None;
+        }
+        let mut v: Vec<u64> = samples_us.iter().copied().collect();
+        v.sort_unstable();
+        let at = |q: f64| {
+            let i = ((q * v.len() as f64).ceil() as usize).clamp(1, v.len()) - 1;
+            v[i] as f64 / 1000.0
+        };
+        Some(Self { p50: at(0.50), p90: at(0.90), p99: at(0.99), max: at(1.0) })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExecSummary {
+    pub kind: CmdKind,
+    pub response_ms: Option<Percentiles>,
+    pub first_fill_ms: Option<Percentiles>,
+    pub accepted: u64,
+    pub post_only_reject: u64,
+    pub insufficient_balance: u64,
+    pub rate_limited: u64,
+    pub not_filled: u64,
+    pub other: u64,
+}
+
+fn log_summary(scope: &str, s: &ExecSummary) {
+    info!(
+        scope,
+        cmd = s.kind.as_str(),
+        resp_p50_ms = ?s.response_ms.map(|p| p.p50),
+        resp_p90_ms = ?s.response_ms.map(|p| p.p90),
+        resp_p99_ms = ?s.response_ms.map(|p| p.p99),
+        resp_max_ms = ?s.response_ms.map(|p| p.max),
+        fill_p50_ms = ?s.first_fill_ms.map(|p| p.p50),
+        fill_p90_ms = ?s.first_fill_ms.map(|p| p.p90),
+        accepted = s.accepted,
+        post_only_reject = s.post_only_reject,
+        insufficient_balance = s.insufficient_balance,
+        rate_limited = s.rate_limited,
+        not_filled = s.not_filled,
+        other = s.other,
+        "exec telemetry"
+    );
+}
+
+/// Log per-ticker and all-ticker summaries every `exec_telemetry_log_s` (0 disables).
+pub async fn run_telemetry_reporter(cfg: Config, shared: Shared) {
+    if cfg.exec_telemetry_log_s == 0 {
+        return;
+    }
+    loop {
+        sleep(Duration::from_secs(cfg.exec_telemetry_log_s)).await;
+        if shared.is_shutting_down() {
+            return;
+        }
+
+        let tickers: Vec<_> = shared.tickers.iter().map(|r| (r.key().clone(), r.value().clone())).collect();
+        let mut all = ExecStats::default();
+        for (ticker, ts) in tickers {
+            let g = ts.mkt.read().await;
+            for kind in CmdKind::ALL {
+                if let Some(s) = g.exec_stats.summary(kind) {
+                    log_summary(&ticker, &s);
+                }
+            }
+            all.merge(&g.exec_stats);
+        }
+        for kind in CmdKind::ALL {
+            if let Some(s) = all.summary(kind) {
+                log_summary("all", &s);
+            }
+        }
+    }
+}
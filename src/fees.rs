@@ -0,0 +1,141 @@
+//! fees.rs
+//!
+//! Kalshi trading fees, per the series' `fee_type` / `fee_multiplier`:
+//! - `quadratic`: takers pay `ceil(mult * C * P * (1 - P))` dollars (rounded up to the cent),
+//!   makers pay nothing
+//! - `quadratic_with_maker_fees`: same for takers; makers pay a quarter of the taker rate
+//! - `flat`: `ceil(mult * C)` dollars regardless of price or liquidity
+//!
+//! P is the fill price in dollars and C the contract count. Everything here is in cc
+//! (see CC_PER_CENT) so it adds straight into cost basis.
+
+use crate::types::CC_PER_CENT;
+
+// Standard Kalshi taker multiplier; used until the series tells us otherwise.
+pub const DEFAULT_TAKER_MULTIPLIER: f64 = 0.07;
+// Maker rate as a fraction of the taker rate (0.0175 vs 0.07).
+const MAKER_SHARE: f64 = 0.25;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Liquidity {
+    Maker,
+    Taker,
+}
+
+impl Liquidity {
+    pub fn from_is_taker(is_taker: bool) -> Self {
+        if is_taker { Liquidity::Taker } else { Liquidity::Maker }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FeeType {
+    Quadratic,
+    QuadraticWithMakerFees,
+    Flat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeeModel {
+    pub fee_type: FeeType,
+    pub multiplier: f64,
+}
+
+impl Default for FeeModel {
+    fn default() -> Self {
+        Self { fee_type: FeeType::Quadratic, multiplier: DEFAULT_TAKER_MULTIPLIER }
+    }
+}
+
+impl FeeModel {
+    /// From `Series.fee_type` / `Series.fee_multiplier`. Unknown types fall back to quadratic.
+    pub fn from_series(fee_type: &str, fee_multiplier: f32) -> Self {
+        let fee_type = match fee_type.trim().to_ascii_lowercase().as_str() {
+            "quadratic_with_maker_fees" => FeeType::QuadraticWithMakerFees,
+            "flat" => FeeType::Flat,
+            _ => FeeType::Quadratic,
+        };
+        // The API's f32 widens to 0.0700000003 for 0.07, enough to tip exact cents over;
+        // multipliers are published to a few decimals, so round that noise away.
+        let multiplier = (fee_multiplier.max(0.0) as f64 * 1e6).round() / 1e6;
+        Self { fee_type, multiplier }
+    }
+
+    /// Fee for one fill of `qty` contracts at `price_cents`, in cc (whole cents, rounded up).
+    pub fn fee_cc(&self, liquidity: Liquidity, price_cents: u8, qty: u64) -> i64 {
+        if qty == 0 {
+            return 0;
+        }
+        let c = qty as f64;
+        let p = price_cents.min(100) as f64 / 100.0;
+
+        let dollars = match (self.fee_type, liquidity) {
+            (FeeType::Flat, _) => self.multiplier * c,
+            (FeeType::Quadratic, Liquidity::Maker) => 0.0,
+            (FeeType::Quadratic | FeeType::QuadraticWithMakerFees, Liquidity::Taker) => {
+                self.multiplier * c * p * (1.0 - p)
+            }
+            (FeeType::QuadraticWithMakerFees, Liquidity::Maker) => {
+                self.multiplier * MAKER_SHARE * c * p * (1.0 - p)
+            }
+        };
+
+        // Round up to the cent; the epsilon keeps exact cents from float noise tipping over.
+        let cents = (dollars * 100.0 - 1e-9).ceil().max(0.0) as i64;
+        cents * CC_PER_CENT
+    }
+
+    pub fn taker_fee_cc(&self, price_cents: u8, qty: u64) -> i64 {
+        self.fee_cc(Liquidity::Taker, price_cents, qty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cents(cc: i64) -> i64 {
+        cc / CC_PER_CENT
+    }
+
+    #[test]
+    fn quadratic_rounds_up_to_the_cent() {
+        let f = FeeModel::default();
+        // 0.07 * 0.25 = 1.75c
+        assert_eq!(cents(f.taker_fee_cc(50, 1)), 2);
+        // Any fee at all is at least a cent.
+        assert_eq!(cents(f.taker_fee_cc(1, 1)), 1);
+        assert_eq!(cents(f.taker_fee_cc(99, 1)), 1);
+        assert_eq!(f.taker_fee_cc(50, 0), 0);
+        assert_eq!(f.fee_cc(Liquidity::Maker, 50, 100), 0);
+    }
+
+    #[test]
+    fn exact_cents_are_not_rounded_up() {
+        let f = FeeModel::default();
+        // $1.75 exactly, though the float product lands a hair above it.
+        assert_eq!(cents(f.taker_fee_cc(50, 100)), 175);
+        // 0.07 * 20 * 0.3 * 0.7 = $0.294 -> 30c
+        assert_eq!(cents(f.taker_fee_cc(30, 20)), 30);
+    }
+
+    #[test]
+    fn maker_fees_and_flat() {
+        let f = FeeModel::from_series("quadratic_with_maker_fees", 0.07);
+        // A quarter of the $1.75 taker fee, rounded up.
+        assert_eq!(cents(f.fee_cc(Liquidity::Maker, 50, 100)), 44);
+        assert_eq!(cents(f.fee_cc(Liquidity::Taker, 50, 100)), 175);
+
+        let f = FeeModel::from_series("FLAT", 0.02);
+        assert_eq!(f.fee_type, FeeType::Flat);
+        assert_eq!(cents(f.fee_cc(Liquidity::Maker, 10, 3)), 6);
+        assert_eq!(cents(f.fee_cc(Liquidity::Taker, 90, 3)), 6);
+    }
+
+    #[test]
+    fn unknown_series_falls_back_to_quadratic() {
+        let f = FeeModel::from_series("something_new", -1.0);
+        assert_eq!(f.fee_type, FeeType::Quadratic);
+        assert_eq!(f.taker_fee_cc(50, 10), 0);
+    }
+}
@@ -0,0 +1,260 @@
+//! governor.rs
+//!
+//! Loss governor: a daily loss limit and drawdown kill switch that survives restarts.
+//!
+//! - Day PnL = realized (settled windows, reported by the settlement tracker) + marked-to-market
+//!   open windows (matched pairs at $1, the unhedged rest at the bid, less cost basis, plus
+//!   what sells already realized). A ticker whose book can't be trusted keeps its last mark.
+//!   Windows between rotation and settlement aren't counted until they settle, and then
+//!   only toward the trading day they closed in.
+//! - The trading day starts at `governor_day_start_utc_h` (UTC); realized PnL and the peak
+//!   reset then. A halt doesn't.
+//! - Halt when day PnL <= -`governor_max_daily_loss_cc`, or when it's fallen
+//!   `governor_max_drawdown_cc` from the day's peak. The engine then pulls every quote and
+//!   places nothing.
+//! - State lives in `governor_state_file` (key=value lines) and is loaded at startup, so a
+//!   halted bot comes back halted. Only `kalshi_bot reset-governor [profile]` clears it.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration as ChronoDuration, TimeZone, Utc};
+use tokio::time::{sleep, Duration};
+use tracing::{error, info, warn};
+
+use crate::config::Config;
+use crate::state::ticker::Market;
+use crate::state::Shared;
+use crate::types::{Side, CC_PER_CENT};
+
+#[derive(Debug, Clone)]
+pub struct Halt {
+    pub reason: String,
+    pub at: String,
+    pub day_pnl_cc: i64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LossGovernor {
+    // Empty = in-memory only.
+    path: String,
+    day: String,
+    realized_cc: i64,
+    peak_cc: i64,
+    // Last marked-to-market value of open windows.
+    open_mtm_cc: i64,
+    halt: Option<Halt>,
+}
+
+fn trading_day(cfg: &Config, now: DateTime<Utc>) -> String {
+    (now - ChronoDuration::hours(cfg.governor_day_start_utc_h as i64))
+        .date_naive()
+        .to_string()
+}
+
+impl LossGovernor {
+    /// Load from `path`; a missing file starts fresh.
+    pub fn load(path: &str) -> Result<Self> {
+        let mut g = Self { path: path.to_string(), ..Self::default() };
+        let raw = match std::fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(g),
+            Err(e) => return Err(e).with_context(|| format!("read {path}")),
+        };
+
+        let kv: HashMap<&str, &str> = raw
+            .lines()
+            .filter_map(|l| l.split_once('='))
+            .map(|(k, v)| (k.trim(), v.trim()))
+            .collect();
+        let num = |k: &str| kv.get(k).and_then(|v| v.parse::<i64>().ok()).unwrap_or(0);
+
+        g.day = kv.get("day").unwrap_or(&"").to_string();
+        g.realized_cc = num("realized_cc");
+        g.peak_cc = num("peak_cc");
+        if kv.get("halted").is_some_and(|v| *v == "1") {
+            g.halt = Some(Halt {
+                reason: kv.get("halt_reason").unwrap_or(&"unknown").to_string(),
+                at: kv.get("halted_at").unwrap_or(&"").to_string(),
+                day_pnl_cc: num("halt_day_pnl_cc"),
+            });
+        }
+        Ok(g)
+    }
+
+    fn save(&self) -> Result<()> {
+        if self.path.is_empty() {
+            return Ok(());
+        }
+        let mut out = format!("day={}\nrealized_cc={}\npeak_cc={}\n", self.day, self.realized_cc, self.peak_cc);
+        match &self.halt {
+            Some(h) => out.push_str(&format!(
+                "halted=1\nhalt_reason={}\nhalted_at={}\nhalt_day_pnl_cc={}\n",
+                h.reason, h.at, h.day_pnl_cc
+            )),
+            None => out.push_str("halted=0\n"),
+        }
+        // Write-then-rename so a crash never leaves a half-written state file.
+        let tmp = format!("{}.tmp", self.path);
+        std::fs::write(&tmp, out).with_context(|| format!("write {tmp}"))?;
+        std::fs::rename(&tmp, &self.path).with_context(|| format!("rename {tmp} -> {}", self.path))?;
+        Ok(())
+    }
+
+    fn persist(&self) {
+        if let Err(e) = self.save() {
+            warn!(path = %self.path, err = ?e, "governor: failed to save state");
+        }
+    }
+
+    pub fn halt(&self) -> Option<&Halt> {
+        self.halt.as_ref()
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halt.is_some()
+    }
+
+    pub fn day_pnl_cc(&self) -> i64 {
+        self.realized_cc + self.open_mtm_cc
+    }
+
+    fn roll_day(&mut self, cfg: &Config, now: DateTime<Utc>) {
+        let day = trading_day(cfg, now);
+        if day != self.day {
+            if !self.day.is_empty() {
+                info!(old = %self.day, new = %day, realized_cc = self.realized_cc, "governor: new trading day");
+            }
+            self.day = day;
+            self.realized_cc = 0;
+            self.peak_cc = 0;
+            self.persist();
+        }
+    }
+
+    /// A window settled: its net PnL is realized for the trading day it closed in (unix
+    /// `close_ts`). One that closed on a day already over is logged and dropped; charging it
+    /// to today would skew today's limits, and yesterday's can't act on it any more.
+    pub fn record_realized(&mut self, cfg: &Config, close_ts: i64, pnl_cc: i64) {
+        self.roll_day(cfg, Utc::now());
+        let closed = Utc.timestamp_opt(close_ts, 0).single().unwrap_or_else(Utc::now);
+        let day = trading_day(cfg, closed);
+        if day != self.day {
+            warn!(window_day = %day, day = %self.day, pnl_cc, "governor: window settled after its trading day ended; not counted");
+            return;
+        }
+        self.realized_cc += pnl_cc;
+        self.persist();
+    }
+
+    /// Re-mark open windows and check the limits. Returns true if this call halted trading.
+    pub fn update(&mut self, cfg: &Config, open_mtm_cc: i64) -> bool {
+        let now = Utc::now();
+        self.roll_day(cfg, now);
+        self.open_mtm_cc = open_mtm_cc;
+
+        let pnl = self.day_pnl_cc();
+        if pnl > self.peak_cc {
+            self.peak_cc = pnl;
+            self.persist();
+        }
+        if self.halt.is_some() {
+            return false;
+        }
+
+        let reason = if pnl <= -cfg.governor_max_daily_loss_cc {
+            "daily_loss"
+        } else if self.peak_cc - pnl >= cfg.governor_max_drawdown_cc {
+            "drawdown"
+        } else {
+            return false;
+        };
+        self.halt = Some(Halt { reason: reason.to_string(), at: now.to_rfc3339(), day_pnl_cc: pnl });
+        self.persist();
+        true
+    }
+}
+
+/// Clear a halt (and start the day's PnL over) in the state file. CLI: `reset-governor`.
+pub fn reset(path: &str) -> Result<()> {
+    let mut g = LossGovernor::load(path)?;
+    match &g.halt {
+        Some(h) => info!(path, reason = %h.reason, halted_at = %h.at, "governor: clearing halt"),
+        None => info!(path, "governor: not halted; resetting day PnL"),
+    }
+    g.halt = None;
+    g.realized_cc = 0;
+    g.peak_cc = 0;
+    g.save()
+}
+
+/// One open window's mark: matched pairs at $1 (they pay that whichever side wins), the
+/// unhedged rest at its bid, less cost, plus realized sells. None = no book to trust right
+/// now (resyncing, stale or faulted, or no bid for the side we'd have to mark).
+fn window_mtm_cc(m: &Market) -> Option<i64> {
+    if m.book.is_resyncing() || m.feed.is_stale() || m.feed.fault.is_some() {
+        return None;
+    }
+    let pos = &m.pos;
+    let pairs = pos.yes_qty.min(pos.no_qty).max(0);
+    let mut value = pairs * 100 * CC_PER_CENT;
+    for (side, qty) in [(Side::Yes, pos.yes_qty), (Side::No, pos.no_qty)] {
+        let rest = qty.max(0) - pairs;
+        if rest > 0 {
+            value += rest * m.book.best_bid(side)? as i64 * CC_PER_CENT;
+        }
+    }
+    Some(value - pos.yes_cost_cc - pos.no_cost_cc + pos.realized_pnl_cc)
+}
+
+/// Open windows marked to market. A ticker without a trustworthy mark keeps its last good
+/// one (held at cost, 0, if it never had one), so a thin or resyncing book can't fake a loss.
+async fn open_mtm_cc(shared: &Shared, last_good: &mut HashMap<String, i64>) -> i64 {
+    let tickers: Vec<_> = shared.tickers.iter().map(|r| (r.key().clone(), r.value().clone())).collect();
+    last_good.retain(|t, _| shared.tickers.contains_key(t));
+    let mut total = 0;
+    for (ticker, ts) in tickers {
+        let g = ts.mkt.read().await;
+        let mark = match window_mtm_cc(&g) {
+            Some(v) => *last_good.entry(ticker).insert_entry(v).get(),
+            None => last_good.get(&ticker).copied().unwrap_or(0),
+        };
+        total += mark;
+    }
+    total
+}
+
+pub async fn run_loss_governor(cfg: Config, shared: Shared) {
+    if let Some(h) = shared.governor().halt() {
+        error!(
+            reason = %h.reason,
+            halted_at = %h.at,
+            day_pnl_cc = h.day_pnl_cc,
+            "governor: HALTED from a previous run; run `reset-governor` to resume"
+        );
+    }
+
+    let mut last_good = HashMap::new();
+    loop {
+        sleep(Duration::from_millis(cfg.governor_poll_ms)).await;
+        if shared.is_shutting_down() {
+            return;
+        }
+
+        let mtm = open_mtm_cc(&shared, &mut last_good).await;
+        let mut g = shared.governor();
+        if g.update(&cfg, mtm) {
+            let peak_cc = g.peak_cc;
+            if let Some(h) = g.halt() {
+                error!(
+                    reason = %h.reason,
+                    day_pnl_cc = h.day_pnl_cc,
+                    peak_cc,
+                    "governor: loss limit hit; HALTING (pulling all quotes, no new orders)"
+                );
+            }
+            drop(g);
+            shared.notify.notify_one();
+        }
+    }
+}
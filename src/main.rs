@@ -6,6 +6,13 @@ mod config;
 mod exec;
 mod market_manager;
 mod report;
+mod shutdown;
+mod exchange_monitor;
+mod fees;
+mod settlement;
+mod governor;
+mod sizing;
+mod shadow;
 
 use anyhow::Result;
 use tokio::sync::mpsc;
@@ -33,6 +40,16 @@ async fn main() -> Result<()> {
 
     let cfg = Config::from_env();
 
+    // `kalshi_bot reset-governor [shadow_profile]`: clear a loss-governor halt and exit.
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(String::as_str) == Some("reset-governor") {
+        let path = match args.get(2) {
+            Some(profile) => Config::shadow_from_env(&cfg, profile).governor_state_file,
+            None => cfg.governor_state_file.clone(),
+        };
+        return governor::reset(&path);
+    }
+
     let api_key_id = env::var("API_KEY").expect("No API_KEY");
     let account = Account::from_file("./private_keys/kalshi_private.pem", api_key_id.as_str())?;
 
@@ -46,10 +63,19 @@ async fn main() -> Result<()> {
     // Create Shared with all current active tickers (so engine/ws start correct)
     let tickers: Vec<String> = active.iter().map(|m| m.market_ticker.clone()).collect();
     let shared = Shared::new(tickers.clone());
+    if cfg.exec_mode.is_paper() {
+        *shared.ledger() = state::ledger::PaperLedger::new(cfg.paper_start_cash_cc);
+    }
+
+    // Loss governor state survives restarts (a halt stays until reset-governor).
+    *shared.governor() = governor::LossGovernor::load(&cfg.governor_state_file)?;
 
     // Seed close_ts/open_ts into Market state for each ticker
     market_manager::seed_shared_times(&shared, &active).await?;
 
+    // Shadow profiles: paper-only copies of the strategy on the same feed + markets
+    let shadows = shadow::start_shadows(&cfg, &http, &active).await?;
+
     // Exec channel (engine + market_manager can both send ExecCommand)
     let (exec_tx, exec_rx) = mpsc::channel(256);
 
@@ -62,8 +88,9 @@ async fn main() -> Result<()> {
         let shared = shared.clone();
         let http = http.clone();
         let cfg = cfg.clone();
+        let shadows = shadows.clone();
         tokio::spawn(async move {
-            let _ = ws::task::run_ws(ws_client, http, cfg, shared, tickers, ws_ctl_rx).await;
+            let _ = ws::task::run_ws(ws_client, http, cfg, shared, tickers, shadows, ws_ctl_rx).await;
         });
     }
 
@@ -77,6 +104,56 @@ async fn main() -> Result<()> {
         });
     }
 
+    // Exchange status/schedule monitor (pauses/resumes trading, gates rotation)
+    {
+        let shared = shared.clone();
+        let http = http.clone();
+        let cfg = cfg.clone();
+        let shadows = shadows.clone();
+        tokio::spawn(async move {
+            let _ = exchange_monitor::run_exchange_monitor(cfg, http, shared, shadows).await;
+        });
+    }
+
+    // Account balance poller for sizing (paper sizes off the simulated ledger)
+    if !cfg.exec_mode.is_paper() {
+        let shared = shared.clone();
+        let http = http.clone();
+        let cfg = cfg.clone();
+        tokio::spawn(async move {
+            let _ = sizing::run_balance_poller(cfg, http, shared).await;
+        });
+    }
+
+    // Loss governor (daily loss / drawdown kill switch)
+    {
+        let shared = shared.clone();
+        let cfg = cfg.clone();
+        tokio::spawn(async move {
+            governor::run_loss_governor(cfg, shared).await;
+        });
+    }
+
+    // Exec telemetry reporter (latency percentiles + outcome counts)
+    {
+        let shared = shared.clone();
+        let cfg = cfg.clone();
+        tokio::spawn(async move {
+            exec::telemetry::run_telemetry_reporter(cfg, shared).await;
+        });
+    }
+
+    // Settlement tracker (market_manager -> settlement): real outcome per closed window
+    let (settle_tx, settle_rx) = mpsc::channel(64);
+    {
+        let shared = shared.clone();
+        let http = http.clone();
+        let cfg = cfg.clone();
+        tokio::spawn(async move {
+            let _ = settlement::run_settlement_tracker(cfg, http, shared, settle_rx).await;
+        });
+    }
+
     // Market manager task (rotates tickers based on close_time)
     {
         let shared = shared.clone();
@@ -84,6 +161,7 @@ async fn main() -> Result<()> {
         let cfg = cfg.clone();
         let ws_ctl_tx = ws_ctl_tx.clone();
         let exec_tx = exec_tx.clone();
+        let shadows = shadows.clone();
 
         tokio::spawn(async move {
             let _ = market_manager::run_market_manager(
@@ -92,13 +170,22 @@ async fn main() -> Result<()> {
                 shared,
                 ws_ctl_tx,
                 exec_tx,
+                settle_tx,
+                shadows,
                 active,
             ).await;
         });
     }
 
-    // Engine runs on the main task
-    engine::task::run_engine(cfg, shared, exec_tx).await?;
+    // Engine runs on the main task until SIGINT/SIGTERM.
+    tokio::select! {
+        res = engine::task::run_engine(cfg.clone(), shared.clone(), exec_tx) => res?,
+        _ = shutdown::wait_for_signal() => {}
+    }
+
+    // Cancel resting orders and write partial-window results before exiting.
+    shutdown::run_shutdown(&cfg, &http, &shared).await;
+    shadow::shutdown_shadows(&http, &shadows).await;
 
     Ok(())
 }
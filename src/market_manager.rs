@@ -8,6 +8,9 @@
 //!   update subscriptions (add new ticker, delete old ticker).
 //!
 //! We do ONE window at a time per series (no overlap).
+//!
+//! Shadow profiles (see shadow.rs) are rotated in lockstep with the main profile.
+//! No rotation while trading isn't allowed (`Shared.trading_allowed`).
 
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
@@ -19,6 +22,9 @@ use kalshi_rs::KalshiClient;
 use kalshi_rs::markets::models::MarketsQuery;
 
 use crate::config::Config;
+use crate::fees::FeeModel;
+use crate::settlement::ClosedWindow;
+use crate::shadow::ShadowProfile;
 use crate::state::Shared;
 use crate::types::{ExecCommand, Side, WsMarketCommand};
 
@@ -28,6 +34,8 @@ pub struct ActiveMarketMeta {
     pub market_ticker: String,
     pub open_ts: i64,
     pub close_ts: i64,
+    // From the series' fee_type / fee_multiplier.
+    pub fees: FeeModel,
 }
 
 /// Parse RFC3339 timestamps like "2026-01-27T23:15:00Z" into epoch seconds (UTC).
@@ -59,6 +67,7 @@ pub async fn fetch_current_market(http: &KalshiClient, series_ticker: &str) -> R
             market_ticker: m.ticker.to_string(),
             open_ts,
             close_ts,
+            fees: fetch_fee_model(http, series_ticker).await,
         });
     }
 
@@ -93,9 +102,21 @@ pub async fn fetch_current_market(http: &KalshiClient, series_ticker: &str) -> R
         market_ticker: ticker,
         open_ts,
         close_ts,
+        fees: fetch_fee_model(http, series_ticker).await,
     })
 }
 
+/// Fee schedule for a series. Falls back to the standard taker schedule if the lookup fails.
+async fn fetch_fee_model(http: &KalshiClient, series_ticker: &str) -> FeeModel {
+    match http.get_series_by_ticker(series_ticker).await {
+        Ok(resp) => FeeModel::from_series(&resp.series.fee_type, resp.series.fee_multiplier),
+        Err(e) => {
+            warn!(series = %series_ticker, err = ?e, "get_series failed; using default fee model");
+            FeeModel::default()
+        }
+    }
+}
+
 pub async fn bootstrap_active_markets(
     http: &KalshiClient,
     series_tickers: &[String],
@@ -120,6 +141,7 @@ pub async fn seed_shared_times(shared: &Shared, markets: &[ActiveMarketMeta]) ->
             let mut g = ts.mkt.write().await;
             g.open_ts = Some(m.open_ts);
             g.close_ts = Some(m.close_ts);
+            g.pos.fee_model = m.fees;
         }
 
         ts.touch(&shared);
@@ -133,33 +155,71 @@ async fn cancel_known_resting(exec_tx: &mpsc::Sender<ExecCommand>, shared: &Shar
     let Some(ts) = shared.tickers.get(ticker) else { return; };
     let mut g = ts.mkt.write().await;
 
-    // If your RestingHint stores order_id, cancel them.
-    // (If order_id is None because we never got ack, we can’t cancel by order_id.)
-    let mut cancels = Vec::new();
-
-    for side in Side::ALL {
-        if let Some(h) = g.resting_hint(side).as_ref() {
-            if let Some(oid) = h.order_id.as_ref() {
-                cancels.push(oid.clone());
-            }
-        }
-    }
+    // Cancel every working order we have an order_id for.
+    // (If order_id is None because we never got ack, we can’t cancel by order_id;
+    // those expire on their own via expiration_ts.)
+    let cancels: Vec<String> = Side::ALL
+        .iter()
+        .flat_map(|&side| g.working(side).iter().filter_map(|w| w.order_id.clone()))
+        .collect();
 
-    // Clear hints so engine won't keep acting on them.
-    for side in Side::ALL {
-        *g.resting_hint_mut(side) = None;
-    }
+    // Forget them so engine won't keep acting on them.
+    g.forget_all_working();
 
     drop(g);
 
     for oid in cancels {
-        let _ = exec_tx.send(ExecCommand::CancelOrder {
+        let _ = exec_tx.send(ExecCommand::Cancel {
             ticker: ticker.to_string(),
             order_id: oid,
+            decided_at: std::time::Instant::now(),
         }).await;
     }
 }
 
+/// Write the end-of-window results row for one profile and hand the window to its
+/// settlement tracker for the real outcome.
+async fn close_window(
+    cfg: &Config,
+    shared: &Shared,
+    settle_tx: &mpsc::Sender<ClosedWindow>,
+    cur: &ActiveMarketMeta,
+) {
+    let Some(ts) = shared.tickers.get(&cur.market_ticker).map(|r| r.value().clone()) else {
+        return;
+    };
+    let pos = {
+        let g = ts.mkt.read().await;
+        g.pos.clone()
+    };
+
+    if let Err(e) = crate::report::append_result_csv(
+        cfg.results_file.as_str(),
+        cur.open_ts,
+        cur.close_ts,
+        &pos,
+        false,
+    )
+    .await
+    {
+        warn!(
+            series = %cur.series_ticker,
+            ticker = %cur.market_ticker,
+            err = ?e,
+            "failed to append window results"
+        );
+    }
+
+    let _ = settle_tx.send(ClosedWindow {
+        series_ticker: cur.series_ticker.clone(),
+        ticker: cur.market_ticker.clone(),
+        open_ts: cur.open_ts,
+        close_ts: cur.close_ts,
+        pos,
+        closed_at: std::time::Instant::now(),
+    }).await;
+}
+
 /// Main loop: watch current close_ts per series and rotate when closed.
 pub async fn run_market_manager(
     cfg: Config,
@@ -167,8 +227,15 @@ pub async fn run_market_manager(
     shared: Shared,
     ws_tx: mpsc::Sender<WsMarketCommand>,
     exec_tx: mpsc::Sender<ExecCommand>,
+    settle_tx: mpsc::Sender<ClosedWindow>,
+    shadows: Vec<ShadowProfile>,
     initial: Vec<ActiveMarketMeta>,
 ) -> Result<()> {
+    // Main profile first, then shadows: (cfg, shared, exec_tx, settle_tx).
+    let profiles: Vec<_> = std::iter::once((&cfg, &shared, &exec_tx, &settle_tx))
+        .chain(shadows.iter().map(|s| (&s.cfg, &s.shared, &s.exec_tx, &s.settle_tx)))
+        .collect();
+
     // Track one active ticker per series (you can have many series -> many simultaneous markets).
     let mut active_by_series: HashMap<String, ActiveMarketMeta> = HashMap::new();
     for m in initial {
@@ -180,6 +247,15 @@ pub async fn run_market_manager(
     loop {
         interval.tick().await;
 
+        // Don't rotate while shutting down; the shutdown path writes the partial rows.
+        if shared.is_shutting_down() {
+            return Ok(());
+        }
+        // Exchange closed or maintenance due (exchange_monitor logs it): rotate once it reopens.
+        if !shared.is_trading_allowed() {
+            continue;
+        }
+
         let now = Utc::now().timestamp();
 
         // Clone keys so we can mutate map while iterating.
@@ -204,43 +280,20 @@ pub async fn run_market_manager(
             // If ticker didn't change, just refresh times (maybe Kalshi updated close_time)
             if next.market_ticker == cur.market_ticker {
                 tracing::info!("series={} active ticker unchanged {}, refreshing times", series, cur.market_ticker);
-                seed_shared_times(&shared, &[next.clone()]).await?;
+                for &(_, shared, _, _) in &profiles {
+                    seed_shared_times(shared, std::slice::from_ref(&next)).await?;
+                }
                 active_by_series.insert(series.clone(), next);
                 continue;
             }
 
-            // NEW: snapshot old ticker position and write end-of-window results
-            let ts_arc = shared
-                .tickers
-                .get(&cur.market_ticker)
-                .map(|r| r.value().clone());
-
-            if let Some(ts) = ts_arc {
-                let pos = {
-                    let g = ts.mkt.read().await;
-                    g.pos.clone()
-                };
-
-                if let Err(e) = crate::report::append_result_csv(
-                    cfg.results_file.as_str(),
-                    cur.open_ts,
-                    cur.close_ts,
-                    &pos,
-                )
-                .await
-                {
-                    warn!(
-                        series = %cur.series_ticker,
-                        ticker = %cur.market_ticker,
-                        err = ?e,
-                        "failed to append window results"
-                    );
-                }
-            }
-
+            // For each profile: write end-of-window results, then
             // 1) Ensure NEW ticker exists in Shared and seed times (so WS snapshot won't be dropped)
-            shared.ensure_ticker(&next.market_ticker);
-            seed_shared_times(&shared, &[next.clone()]).await?;
+            for &(cfg, shared, _, settle_tx) in &profiles {
+                close_window(cfg, shared, settle_tx, &cur).await;
+                shared.ensure_ticker(&next.market_ticker);
+                seed_shared_times(shared, std::slice::from_ref(&next)).await?;
+            }
 
             // 2) Tell WS task to update subscriptions:
             //    - add new ticker
@@ -250,11 +303,13 @@ pub async fn run_market_manager(
                 remove: vec![cur.market_ticker.clone()],
             }).await;
 
-            // 3) Optional: cancel known resting orders on old ticker
-            cancel_known_resting(&exec_tx, &shared, &cur.market_ticker).await;
+            for &(_, shared, exec_tx, _) in &profiles {
+                // 3) Optional: cancel known resting orders on old ticker
+                cancel_known_resting(exec_tx, shared, &cur.market_ticker).await;
 
-            // 4) Remove old ticker from Shared to stop engine processing it
-            shared.remove_ticker(&cur.market_ticker);
+                // 4) Remove old ticker from Shared to stop engine processing it
+                shared.remove_ticker(&cur.market_ticker);
+            }
 
             // 5) Update our map
             active_by_series.insert(series.clone(), next);
@@ -1,5 +1,5 @@
 // src/report.rs
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::state::position::Position;
 use crate::types::CC_PER_CENT;
@@ -8,7 +8,7 @@ use anyhow::{Context, Result};
 use chrono::{TimeZone, Utc};
 use std::io::ErrorKind;
 use tokio::fs::OpenOptions;
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 
 fn cc_to_cents(cc: i64) -> f64 {
     cc as f64 / CC_PER_CENT as f64
@@ -33,6 +33,8 @@ pub fn log_position(ticker: &str, pos: &Position) {
         no_avg_cents = ?no_avg_cents,
         pair_cost_cents = ?pair_cost_cents,
         pair_cost_dollars = ?pair_cost_dollars,
+        realized_pnl_dollars = cc_to_dollars(pos.realized_pnl_cc),
+        fees_dollars = cc_to_dollars(pos.fees_cc),
         "position snapshot"
     );
 }
@@ -53,19 +55,42 @@ fn fmt_opt_4(v: Option<f64>) -> String {
     v.map(|x| format!("{x:.4}")).unwrap_or_else(|| "".to_string())
 }
 
+const RESULTS_HEADER: &str = "run_ts_utc,open_time_utc,close_time_utc,yes_qty,no_qty,yes_avg_cents,no_avg_cents,pair_cost_cents,pair_cost_dollars,pnl_yes_win,pnl_no_win,realized_pnl,fees,window_status";
+
+/// True if `p` needs a header written: missing or empty. A file written under a different
+/// header (older column set) is moved aside to `<path>.<unix_ts>.old` first, so rows never
+/// land under the wrong columns.
+async fn results_needs_header(p: &std::path::Path) -> Result<bool> {
+    let first_line = match tokio::fs::File::open(p).await {
+        Ok(f) => BufReader::new(f).lines().next_line().await?.unwrap_or_default(),
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(true),
+        Err(e) => return Err(e).context("open(results_file)"),
+    };
+    if first_line.is_empty() {
+        return Ok(true);
+    }
+    if first_line.trim_end() == RESULTS_HEADER {
+        return Ok(false);
+    }
+    let old = format!("{}.{}.old", p.display(), Utc::now().timestamp());
+    tokio::fs::rename(p, &old)
+        .await
+        .with_context(|| format!("rotate results file {} -> {old}", p.display()))?;
+    warn!(path = %p.display(), moved_to = %old, "results file has an old header; rotated it");
+    Ok(true)
+}
+
+/// `partial` marks a window we left early (shutdown) instead of one that ran to close.
 pub async fn append_result_csv(
     path: &str,
     open_ts: i64,
     close_ts: i64,
     pos: &Position,
+    partial: bool,
 ) -> Result<()> {
     let p = std::path::Path::new(path);
 
-    let needs_header = match tokio::fs::metadata(p).await {
-        Ok(m) => m.len() == 0,
-        Err(e) if e.kind() == ErrorKind::NotFound => true,
-        Err(e) => return Err(e).context("metadata(results_file)"),
-    };
+    let needs_header = results_needs_header(p).await?;
 
     let mut f = OpenOptions::new()
         .create(true)
@@ -75,8 +100,7 @@ pub async fn append_result_csv(
         .with_context(|| format!("open results file {}", p.display()))?;
 
     if needs_header {
-        let header = "run_ts_utc,open_time_utc,close_time_utc,yes_qty,no_qty,yes_avg_cents,no_avg_cents,pair_cost_cents,pair_cost_dollars,pnl_yes_win,pnl_no_win\n";
-        f.write_all(header.as_bytes()).await?;
+        f.write_all(format!("{RESULTS_HEADER}\n").as_bytes()).await?;
     }
 
     let run_ts = Utc::now().to_rfc3339();
@@ -95,11 +119,19 @@ pub async fn append_result_csv(
     let yes_qty = pos.yes_qty.max(0) as f64;
     let no_qty  = pos.no_qty.max(0) as f64;
 
-    let pnl_yes_win_dollars = yes_qty - total_cost_dollars;
-    let pnl_no_win_dollars  = no_qty  - total_cost_dollars;
+    // Anything already unwound by sells is locked in regardless of outcome.
+    let realized_dollars = cc_to_dollars(pos.realized_pnl_cc);
+
+    let pnl_yes_win_dollars = yes_qty - total_cost_dollars + realized_dollars;
+    let pnl_no_win_dollars  = no_qty  - total_cost_dollars + realized_dollars;
     
+    // Already included in the PnL figures (buy fees are in cost basis, sell fees in realized).
+    let fees_dollars = cc_to_dollars(pos.fees_cc);
+
+    let window_status = if partial { "partial" } else { "closed" };
+
     let line = format!(
-        "{run_ts},{open_time},{close_time},{},{},{},{},{},{},{},{}\n",
+        "{run_ts},{open_time},{close_time},{},{},{},{},{},{},{},{},{},{:.4},{window_status}\n",
         pos.yes_qty,
         pos.no_qty,
         fmt_opt_2(yes_avg_cents),
@@ -108,6 +140,60 @@ pub async fn append_result_csv(
         fmt_opt_4(pair_cost_dollars),
         pnl_yes_win_dollars,
         pnl_no_win_dollars,
+        realized_dollars,
+        fees_dollars,
+    );
+
+    f.write_all(line.as_bytes()).await?;
+    f.flush().await?;
+    Ok(())
+}
+
+/// One row per window once its market has settled (see settlement.rs).
+pub async fn append_settlement_csv(
+    path: &str,
+    w: &crate::settlement::ClosedWindow,
+    s: &crate::settlement::WindowSettlement,
+) -> Result<()> {
+    let p = std::path::Path::new(path);
+
+    let needs_header = match tokio::fs::metadata(p).await {
+        Ok(m) => m.len() == 0,
+        Err(e) if e.kind() == ErrorKind::NotFound => true,
+        Err(e) => return Err(e).context("metadata(settlements_file)"),
+    };
+
+    let mut f = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(p)
+        .await
+        .with_context(|| format!("open settlements file {}", p.display()))?;
+
+    if needs_header {
+        let header = "settled_ts_utc,series,ticker,open_time_utc,close_time_utc,result,yes_settle_cents,yes_qty,no_qty,cost,revenue,fees,realized_pnl,net_pnl,source\n";
+        f.write_all(header.as_bytes()).await?;
+    }
+
+    let pos = &w.pos;
+    // Cost is fee-inclusive; fees are shown separately for reference.
+    let line = format!(
+        "{},{},{},{},{},{},{},{},{},{:.4},{:.4},{:.4},{:.4},{:.4},{}\n",
+        Utc::now().to_rfc3339(),
+        w.series_ticker,
+        w.ticker,
+        fmt_ts_rfc3339(w.open_ts),
+        fmt_ts_rfc3339(w.close_ts),
+        s.result,
+        s.yes_settle_cents,
+        pos.yes_qty,
+        pos.no_qty,
+        cc_to_dollars(pos.yes_cost_cc + pos.no_cost_cc),
+        cc_to_dollars(s.revenue_cc),
+        cc_to_dollars(pos.fees_cc),
+        cc_to_dollars(pos.realized_pnl_cc),
+        cc_to_dollars(s.net_pnl_cc(pos)),
+        s.source.as_str(),
     );
 
     f.write_all(line.as_bytes()).await?;
@@ -0,0 +1,209 @@
+//! settlement.rs
+//!
+//! What a window actually paid out. `results.csv` is written at rotation with the
+//! hypothetical yes-wins / no-wins PnL; this tracker keeps every rotated-out window until
+//! its market settles and then appends the real outcome to `settlements_file`.
+//!
+//! - Outcome: `get_market` (`result`, `settlement_value`) once the market is settled.
+//!   Paper mode resolves the simulated position against that same result.
+//! - Live mode prefers the exchange's own figure from `get_settlements` when it's there.
+//!
+//! Windows still pending at shutdown are dropped (logged); this is reporting only.
+
+use std::time::Instant;
+
+use anyhow::Result;
+use kalshi_rs::KalshiClient;
+use kalshi_rs::portfolio::models::GetSettlementsParams;
+use tokio::sync::mpsc;
+use tokio::time::{interval, Duration, MissedTickBehavior};
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::state::position::Position;
+use crate::state::Shared;
+use crate::types::CC_PER_CENT;
+
+/// A window we stopped trading, waiting for its market to settle.
+#[derive(Debug, Clone)]
+pub struct ClosedWindow {
+    pub series_ticker: String,
+    pub ticker: String,
+    pub open_ts: i64,
+    pub close_ts: i64,
+    // Position as of rotation.
+    pub pos: Position,
+    pub closed_at: Instant,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettlementSource {
+    Market,
+    Settlements,
+}
+
+impl SettlementSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SettlementSource::Market => "market",
+            SettlementSource::Settlements => "settlements",
+        }
+    }
+}
+
+/// The resolved outcome of one window.
+#[derive(Debug, Clone)]
+pub struct WindowSettlement {
+    pub result: String,
+    // Payout per YES contract (cents); NO pays 100 - this.
+    pub yes_settle_cents: u32,
+    pub revenue_cc: i64,
+    pub source: SettlementSource,
+}
+
+impl WindowSettlement {
+    /// Settlement payout + what sells already locked in - everything we paid (fees included).
+    pub fn net_pnl_cc(&self, pos: &Position) -> i64 {
+        self.revenue_cc + pos.realized_pnl_cc - pos.yes_cost_cc - pos.no_cost_cc
+    }
+}
+
+/// Payout for the held position given the YES settlement value.
+fn revenue_cc(pos: &Position, yes_settle_cents: u32) -> i64 {
+    let v = yes_settle_cents.min(100) as i64;
+    (pos.yes_qty.max(0) * v + pos.no_qty.max(0) * (100 - v)) * CC_PER_CENT
+}
+
+async fn resolve_from_market(client: &KalshiClient, w: &ClosedWindow) -> Result<Option<WindowSettlement>> {
+    let m = client.get_market(&w.ticker).await?.market;
+    if !matches!(m.status.as_str(), "settled" | "finalized") {
+        return Ok(None);
+    }
+
+    let result = m.result.unwrap_or_default();
+    let yes_settle_cents = match (m.settlement_value, result.as_str()) {
+        (Some(v), _) => v,
+        (None, "yes") => 100,
+        (None, "no") => 0,
+        _ => return Ok(None),
+    };
+
+    Ok(Some(WindowSettlement {
+        revenue_cc: revenue_cc(&w.pos, yes_settle_cents),
+        result,
+        yes_settle_cents,
+        source: SettlementSource::Market,
+    }))
+}
+
+/// Live only: the exchange's settlement record for this ticker, if it's been posted.
+async fn resolve_from_settlements(client: &KalshiClient, w: &ClosedWindow) -> Result<Option<WindowSettlement>> {
+    let params = GetSettlementsParams {
+        ticker: Some(w.ticker.clone()),
+        limit: Some(1),
+        ..Default::default()
+    };
+    let resp = client.get_settlements(&params).await?;
+    let Some(s) = resp.settlements.into_iter().find(|s| s.ticker == w.ticker) else {
+        return Ok(None);
+    };
+
+    Ok(Some(WindowSettlement {
+        yes_settle_cents: match s.market_result.as_str() {
+            "yes" => 100,
+            "no" => 0,
+            _ => s.value as u32,
+        },
+        result: s.market_result,
+        revenue_cc: s.revenue * CC_PER_CENT,
+        source: SettlementSource::Settlements,
+    }))
+}
+
+async fn resolve(cfg: &Config, client: &KalshiClient, w: &ClosedWindow) -> Result<Option<WindowSettlement>> {
+    if !cfg.exec_mode.is_paper() {
+        match resolve_from_settlements(client, w).await {
+            Ok(Some(s)) => return Ok(Some(s)),
+            Ok(None) => {}
+            Err(e) => warn!(ticker = %w.ticker, err = ?e, "settlement: get_settlements failed; trying the market"),
+        }
+    }
+    resolve_from_market(client, w).await
+}
+
+/// Unresolved (not settled yet, or the lookup failed): poll again, unless it's been
+/// `settlement_max_wait_s` since close.
+fn retry_or_give_up(cfg: &Config, w: ClosedWindow, still_pending: &mut Vec<ClosedWindow>) {
+    if w.closed_at.elapsed() >= Duration::from_secs(cfg.settlement_max_wait_s) {
+        warn!(ticker = %w.ticker, "settlement: still unresolved after max wait; giving up");
+    } else {
+        still_pending.push(w);
+    }
+}
+
+pub async fn run_settlement_tracker(
+    cfg: Config,
+    client: std::sync::Arc<KalshiClient>,
+    shared: Shared,
+    mut rx: mpsc::Receiver<ClosedWindow>,
+) -> Result<()> {
+    let mut pending: Vec<ClosedWindow> = Vec::new();
+    let mut tick = interval(Duration::from_millis(cfg.settlement_poll_ms));
+    tick.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            w = rx.recv() => {
+                let Some(w) = w else { break; };
+                info!(ticker = %w.ticker, series = %w.series_ticker, "settlement: tracking closed window");
+                pending.push(w);
+                continue;
+            }
+            _ = tick.tick() => {}
+        }
+
+        if shared.is_shutting_down() {
+            break;
+        }
+
+        let mut still_pending = Vec::with_capacity(pending.len());
+        for w in pending.drain(..) {
+            match resolve(&cfg, &client, &w).await {
+                Ok(Some(s)) => {
+                    let net_pnl_cc = s.net_pnl_cc(&w.pos);
+                    info!(
+                        ticker = %w.ticker,
+                        result = %s.result,
+                        yes_settle_cents = s.yes_settle_cents,
+                        revenue_cc = s.revenue_cc,
+                        fees_cc = w.pos.fees_cc,
+                        net_pnl_cc,
+                        source = s.source.as_str(),
+                        "settlement: window settled"
+                    );
+                    shared.governor().record_realized(&cfg, w.close_ts, net_pnl_cc);
+                    if cfg.exec_mode.is_paper() {
+                        let mut ledger = shared.ledger();
+                        ledger.credit(s.revenue_cc);
+                        info!(ticker = %w.ticker, cash_cc = ledger.cash_cc, "PAPER settlement payout credited");
+                    }
+                    if let Err(e) = crate::report::append_settlement_csv(&cfg.settlements_file, &w, &s).await {
+                        warn!(ticker = %w.ticker, err = ?e, "failed to append settlement row");
+                    }
+                }
+                Ok(None) => retry_or_give_up(&cfg, w, &mut still_pending),
+                Err(e) => {
+                    warn!(ticker = %w.ticker, err = ?e, "settlement: lookup failed");
+                    retry_or_give_up(&cfg, w, &mut still_pending);
+                }
+            }
+        }
+        pending = still_pending;
+    }
+
+    if !pending.is_empty() {
+        let tickers: Vec<&str> = pending.iter().map(|w| w.ticker.as_str()).collect();
+        warn!(?tickers, "settlement: exiting with unsettled windows");
+    }
+    Ok(())
+}
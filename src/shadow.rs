@@ -0,0 +1,108 @@
+//! shadow.rs
+//!
+//! Shadow profiles: alternative `Config`s run in paper mode on the same WS feed, and the
+//! same markets, the main profile trades (`SHADOW_PROFILES`, see `Config::shadow_from_env`).
+//!
+//! - Each shadow has its own `Shared`: per-ticker `Market` (book, position, orders, hints)
+//!   and paper ledger. Nothing in it is shared with the main profile.
+//! - Its own engine, paper exec, settlement and loss-governor tasks run against that `Shared`.
+//! - The WS task feeds book/trade updates into every shadow; user fills only touch main.
+//! - The market manager rotates shadows with main; results and settlements go to the
+//!   profile's own files.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use tokio::sync::mpsc;
+use tracing::{info, info_span, Instrument};
+
+use kalshi_rs::KalshiClient;
+
+use crate::config::Config;
+use crate::governor::LossGovernor;
+use crate::market_manager::ActiveMarketMeta;
+use crate::settlement::ClosedWindow;
+use crate::state::ledger::PaperLedger;
+use crate::state::Shared;
+use crate::types::ExecCommand;
+
+#[derive(Debug, Clone)]
+pub struct ShadowProfile {
+    pub name: String,
+    pub cfg: Config,
+    pub shared: Shared,
+    pub exec_tx: mpsc::Sender<ExecCommand>,
+    pub settle_tx: mpsc::Sender<ClosedWindow>,
+}
+
+/// Build every profile in `SHADOW_PROFILES` and spawn its engine, exec and settlement tasks.
+pub async fn start_shadows(
+    base: &Config,
+    http: &Arc<KalshiClient>,
+    active: &[ActiveMarketMeta],
+) -> Result<Vec<ShadowProfile>> {
+    let tickers: Vec<String> = active.iter().map(|m| m.market_ticker.clone()).collect();
+    let mut out = Vec::new();
+
+    for name in crate::config::shadow_profile_names() {
+        let cfg = Config::shadow_from_env(base, &name);
+        let shared = Shared::new(tickers.clone());
+        *shared.ledger() = PaperLedger::new(cfg.paper_start_cash_cc);
+        *shared.governor() = LossGovernor::load(&cfg.governor_state_file)?;
+        crate::market_manager::seed_shared_times(&shared, active).await?;
+
+        let (exec_tx, exec_rx) = mpsc::channel(256);
+        let (settle_tx, settle_rx) = mpsc::channel(64);
+        let span = info_span!("shadow", profile = %name);
+
+        {
+            let (cfg, http, shared) = (cfg.clone(), http.clone(), shared.clone());
+            tokio::spawn(
+                async move {
+                    let _ = crate::exec::task::run_exec(cfg, http, shared, exec_rx).await;
+                }
+                .instrument(span.clone()),
+            );
+        }
+        {
+            let (cfg, shared, exec_tx) = (cfg.clone(), shared.clone(), exec_tx.clone());
+            tokio::spawn(
+                async move {
+                    let _ = crate::engine::task::run_engine(cfg, shared, exec_tx).await;
+                }
+                .instrument(span.clone()),
+            );
+        }
+        {
+            let (cfg, shared) = (cfg.clone(), shared.clone());
+            tokio::spawn(crate::governor::run_loss_governor(cfg, shared).instrument(span.clone()));
+        }
+        {
+            let (cfg, http, shared) = (cfg.clone(), http.clone(), shared.clone());
+            tokio::spawn(
+                async move {
+                    let _ = crate::settlement::run_settlement_tracker(cfg, http, shared, settle_rx).await;
+                }
+                .instrument(span.clone()),
+            );
+        }
+
+        info!(
+            profile = %name,
+            results_file = %cfg.results_file,
+            settlements_file = %cfg.settlements_file,
+            "shadow profile started (paper)"
+        );
+        out.push(ShadowProfile { name, cfg, shared, exec_tx, settle_tx });
+    }
+    Ok(out)
+}
+
+/// Paper-cancel each shadow's resting orders and write its partial results rows.
+pub async fn shutdown_shadows(http: &KalshiClient, shadows: &[ShadowProfile]) {
+    for s in shadows {
+        crate::shutdown::run_shutdown(&s.cfg, http, &s.shared)
+            .instrument(info_span!("shadow", profile = %s.name))
+            .await;
+    }
+}
@@ -0,0 +1,206 @@
+//! shutdown.rs
+//!
+//! SIGINT/SIGTERM handling.
+//!
+//! - Stops the engine (and exec placing) via `Shared::begin_shutdown`.
+//! - Cancels every resting order we know about, then sweeps the exchange for anything
+//!   still resting on our tickers (late acks / unknown order_id) and batch-cancels it.
+//! - Waits (bounded by `shutdown_cancel_timeout_ms`) until the exchange shows nothing resting.
+//! - Appends a "partial" results row for every window we were in.
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use tokio::time::{sleep, Duration};
+use tracing::{info, warn};
+
+use kalshi_rs::KalshiClient;
+
+use crate::config::Config;
+use crate::exec::{http, paper};
+use crate::state::orders::OrderStatus;
+use crate::state::Shared;
+use crate::state::ticker::TickerState;
+
+/// Resolves on the first SIGINT (Ctrl-C) or SIGTERM.
+pub async fn wait_for_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut term = match signal(SignalKind::terminate()) {
+            Ok(s) => s,
+            Err(e) => {
+                warn!("cannot install SIGTERM handler: {e:?}");
+                let _ = tokio::signal::ctrl_c().await;
+                info!("SIGINT received");
+                return;
+            }
+        };
+
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => info!("SIGINT received"),
+            _ = term.recv() => info!("SIGTERM received"),
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+        info!("Ctrl-C received");
+    }
+}
+
+fn snapshot_tickers(shared: &Shared) -> Vec<(String, Arc<TickerState>)> {
+    shared
+        .tickers
+        .iter()
+        .map(|r| (r.key().clone(), r.value().clone()))
+        .collect()
+}
+
+/// Order ids we believe are live (resting, or acked while still marked pending).
+async fn known_live_order_ids(ts: &TickerState) -> (Vec<String>, usize) {
+    let g = ts.mkt.read().await;
+    let mut ids = Vec::new();
+    let mut unknown = 0usize;
+
+    for rec in g.orders.by_client.values() {
+        if !matches!(rec.status, OrderStatus::Resting | OrderStatus::PendingAck) {
+            continue;
+        }
+        match rec.order_id.as_ref() {
+            Some(oid) => ids.push(oid.clone()),
+            None => unknown += 1,
+        }
+    }
+    (ids, unknown)
+}
+
+async fn mark_canceled(shared: &Shared, ticker: &str, order_id: &str) {
+    let Some(ts) = shared.tickers.get(ticker) else { return; };
+    let mut g = ts.mkt.write().await;
+    g.orders.set_status_by_order(order_id, OrderStatus::Canceled);
+    g.forget_working_order(order_id);
+}
+
+async fn cancel_all_paper(shared: &Shared) {
+    for (ticker, ts) in snapshot_tickers(shared) {
+        let (ids, _unknown) = known_live_order_ids(&ts).await;
+        for oid in ids {
+            paper::paper_cancel(shared, &ticker, &oid).await;
+        }
+
+        // Paper acks inline, so anything still pending never reached the "exchange".
+        let mut g = ts.mkt.write().await;
+        for rec in g.orders.by_client.values_mut() {
+            if rec.status == OrderStatus::PendingAck {
+                rec.status = OrderStatus::Canceled;
+            }
+        }
+        g.forget_all_working();
+    }
+}
+
+async fn cancel_all_live(cfg: &Config, client: &KalshiClient, shared: &Shared) {
+    let tickers = snapshot_tickers(shared);
+
+    // 1) Cancel everything we have an order_id for.
+    for (ticker, ts) in tickers.iter() {
+        let (ids, unknown) = known_live_order_ids(ts).await;
+        if unknown > 0 {
+            info!(ticker = %ticker, unknown, "shutdown: orders without order_id; will sweep exchange");
+        }
+        for oid in ids {
+            match http::cancel(client, &oid).await {
+                Ok(()) => {
+                    info!(ticker = %ticker, order_id = %oid, "shutdown: canceled");
+                    mark_canceled(shared, ticker, &oid).await;
+                }
+                Err(e) => warn!(ticker = %ticker, order_id = %oid, "shutdown: cancel failed: {e:?}"),
+            }
+        }
+    }
+
+    // 2) Sweep: whatever the exchange still shows resting gets batch-canceled,
+    //    until nothing is left or we run out of time.
+    let deadline = Instant::now() + Duration::from_millis(cfg.shutdown_cancel_timeout_ms);
+    loop {
+        let mut remaining = 0usize;
+
+        for (ticker, _) in tickers.iter() {
+            let resting = match http::list_resting(client, ticker).await {
+                Ok(v) => v,
+                Err(e) => {
+                    warn!(ticker = %ticker, "shutdown: list resting failed: {e:?}");
+                    remaining += 1;
+                    continue;
+                }
+            };
+            if resting.is_empty() {
+                continue;
+            }
+
+            remaining += resting.len();
+            let ids: Vec<String> = resting.into_iter().map(|o| o.order_id).collect();
+            match http::batch_cancel(client, ids).await {
+                Ok(orders) => {
+                    for o in orders {
+                        mark_canceled(shared, ticker, &o.order_id).await;
+                    }
+                }
+                Err(e) => warn!(ticker = %ticker, "shutdown: batch cancel failed: {e:?}"),
+            }
+        }
+
+        if remaining == 0 {
+            info!("shutdown: no resting orders left on exchange");
+            return;
+        }
+        if Instant::now() >= deadline {
+            warn!(remaining, "shutdown: cancel deadline hit with orders possibly still resting");
+            return;
+        }
+        sleep(Duration::from_millis(250)).await;
+    }
+}
+
+async fn write_partial_results(cfg: &Config, shared: &Shared) {
+    for (ticker, ts) in snapshot_tickers(shared) {
+        let (open_ts, close_ts, pos) = {
+            let g = ts.mkt.read().await;
+            (g.open_ts, g.close_ts, g.pos.clone())
+        };
+        let (Some(open_ts), Some(close_ts)) = (open_ts, close_ts) else {
+            warn!(ticker = %ticker, "shutdown: no window times; skipping partial row");
+            continue;
+        };
+
+        crate::report::log_position(&ticker, &pos);
+        if let Err(e) = crate::report::append_result_csv(
+            cfg.results_file.as_str(),
+            open_ts,
+            close_ts,
+            &pos,
+            true,
+        )
+        .await
+        {
+            warn!(ticker = %ticker, err = ?e, "failed to append partial window results");
+        }
+    }
+}
+
+/// Run the whole shutdown sequence. Never fails; problems are logged.
+pub async fn run_shutdown(cfg: &Config, client: &KalshiClient, shared: &Shared) {
+    shared.begin_shutdown();
+
+    if cfg.exec_mode.is_paper() {
+        cancel_all_paper(shared).await;
+    } else {
+        cancel_all_live(cfg, client, shared).await;
+    }
+
+    write_partial_results(cfg, shared).await;
+    info!("shutdown complete");
+}
@@ -0,0 +1,234 @@
+//! sizing.rs
+//!
+//! Balance-aware sizing: order sizes follow the money the account actually has.
+//!
+//! - Live: `run_balance_poller` keeps `Shared.capital` fresh from `get_balance` and
+//!   `get_total_resting_order_value`. Paper reads the simulated ledger instead.
+//! - Free capital = balance - what resting buys already hold - `sizing_reserve_cc`. Resting
+//!   buys count at the larger of the exchange's figure and our own live orders, so orders
+//!   placed since the last poll are never spent twice.
+//! - Each engine pass (`view`) splits free capital evenly across tickers; a ticker may also
+//!   reuse what its own resting buys hold. `apply` shrinks this tick's buys (takers first,
+//!   then the short side's quotes) until they fit, so free capital never goes negative.
+//! - Per-window inventory budget: `sizing_window_fraction` of equity per ticker caps a
+//!   window's cost basis. Buys that close the yes/no gap are exempt; only free capital binds them.
+//! - An insufficient-balance reject backs the ticker off new buys for `sizing_reject_backoff_ms`
+//!   and triggers a balance refresh, instead of retrying into the same reject every tick.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+use anyhow::Result;
+use tokio::time::{sleep, Duration};
+use tracing::{debug, info, warn};
+
+use kalshi_rs::KalshiClient;
+
+use crate::config::Config;
+use crate::engine::order_manager::{DesiredState, QuoteIntent};
+use crate::state::ledger::buy_collateral_cc;
+use crate::state::orders::OrderStatus;
+use crate::state::ticker::Market;
+use crate::state::Shared;
+use crate::types::{Action, Side, CC_PER_CENT};
+
+/// Last account figures from the exchange (live mode).
+#[derive(Debug, Clone, Default)]
+pub struct Capital {
+    pub balance_cc: i64,
+    pub portfolio_value_cc: i64,
+    pub resting_cc: i64,
+    // None until the first successful poll.
+    pub updated_at: Option<Instant>,
+}
+
+/// What one engine pass may spend.
+#[derive(Debug, Clone)]
+pub struct CapitalView {
+    pub free_cc: i64,
+    pub equity_cc: i64,
+    tickers: i64,
+    // Collateral each ticker's own live buys hold, as of this pass.
+    own_resting_cc: HashMap<String, i64>,
+}
+
+/// Collateral our live buy orders on this ticker hold (remaining qty + taker fee).
+pub fn resting_buy_cc(m: &Market) -> i64 {
+    m.orders
+        .by_client
+        .values()
+        .filter(|r| r.action == Action::Buy && matches!(r.status, OrderStatus::Resting | OrderStatus::PendingAck))
+        .map(|r| buy_collateral_cc(&m.pos.fee_model, r.price_cents, r.qty.saturating_sub(r.filled_qty)))
+        .sum()
+}
+
+/// Capital for this pass. None = no balance known yet (live); send no new buys then.
+pub async fn view(cfg: &Config, shared: &Shared) -> Option<CapitalView> {
+    let tickers: Vec<_> = shared.tickers.iter().map(|r| (r.key().clone(), r.value().clone())).collect();
+    let mut own_resting_cc = HashMap::with_capacity(tickers.len());
+    let mut cost_cc = 0;
+    for (ticker, ts) in tickers {
+        let g = ts.mkt.read().await;
+        own_resting_cc.insert(ticker, resting_buy_cc(&g));
+        cost_cc += g.pos.yes_cost_cc + g.pos.no_cost_cc;
+    }
+    let local_resting: i64 = own_resting_cc.values().sum();
+
+    let (balance_cc, resting_cc, equity_cc) = if cfg.exec_mode.is_paper() {
+        let ledger = shared.ledger();
+        (ledger.cash_cc, ledger.reserved_cc(), ledger.cash_cc + cost_cc)
+    } else {
+        let c = shared.capital();
+        c.updated_at?;
+        (c.balance_cc, c.resting_cc, c.balance_cc + c.portfolio_value_cc)
+    };
+
+    Some(CapitalView {
+        free_cc: balance_cc - resting_cc.max(local_resting) - cfg.sizing_reserve_cc,
+        equity_cc,
+        tickers: own_resting_cc.len().max(1) as i64,
+        own_resting_cc,
+    })
+}
+
+/// Largest qty <= `qty` whose collateral fits in `left_cc`. Collateral only grows with qty,
+/// so this bisects instead of trying every size.
+fn fit_qty(m: &Market, price_cents: u8, qty: u64, left_cc: i64) -> u64 {
+    let fits = |q| buy_collateral_cc(&m.pos.fee_model, price_cents, q) <= left_cc;
+    if fits(qty) {
+        return qty;
+    }
+    // fits(lo) (or lo == 0), !fits(hi)
+    let (mut lo, mut hi) = (0, qty);
+    while hi - lo > 1 {
+        let mid = lo + (hi - lo) / 2;
+        if fits(mid) {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    lo
+}
+
+/// Shrinks buys to the budget as they're visited; the short side's gap is exempt from
+/// the window budget.
+struct Budget {
+    hard_cc: i64,
+    window_cc: i64,
+    short: Option<Side>,
+    gap: u64,
+}
+
+impl Budget {
+    fn take(&mut self, m: &Market, side: Side, price_cents: u8, qty: u64) -> u64 {
+        let hedge = if Some(side) == self.short { qty.min(self.gap) } else { 0 };
+        let hedge = fit_qty(m, price_cents, hedge, self.hard_cc);
+        let hedge_cc = buy_collateral_cc(&m.pos.fee_model, price_cents, hedge);
+        self.hard_cc -= hedge_cc;
+        self.gap -= hedge;
+
+        let more = fit_qty(m, price_cents, qty - hedge, self.hard_cc.min(self.window_cc));
+        let more_cc = buy_collateral_cc(&m.pos.fee_model, price_cents, more);
+        self.hard_cc -= more_cc;
+        self.window_cc -= more_cc;
+        hedge + more
+    }
+}
+
+/// No new buys this tick: sells still go out, resting quotes are left alone.
+pub fn hold_buys(desired: &mut DesiredState) {
+    desired.takers.retain(|t| t.action == Action::Sell);
+    for side in Side::ALL {
+        if matches!(desired.quote(side), QuoteIntent::Ladder(_)) {
+            *desired.quote_mut(side) = QuoteIntent::Leave;
+        }
+    }
+}
+
+/// Fit this tick's buys for one ticker into what the account can fund.
+pub fn apply(cfg: &Config, c: &CapitalView, ticker: &str, desired: &mut DesiredState, m: &Market, now: Instant) {
+    // Recently rejected for balance: keep what's resting, add nothing.
+    if m.buy_backoff_until.is_some_and(|t| now < t) {
+        hold_buys(desired);
+        return;
+    }
+
+    let own = c.own_resting_cc.get(ticker).copied().unwrap_or(0);
+    let window_budget_cc = (c.equity_cc as f64 * cfg.sizing_window_fraction) as i64 / c.tickers;
+    let (short, gap) = match m.pos.yes_qty.cmp(&m.pos.no_qty) {
+        std::cmp::Ordering::Less => (Some(Side::Yes), (m.pos.no_qty - m.pos.yes_qty) as u64),
+        std::cmp::Ordering::Greater => (Some(Side::No), (m.pos.yes_qty - m.pos.no_qty) as u64),
+        std::cmp::Ordering::Equal => (None, 0),
+    };
+    let mut budget = Budget {
+        hard_cc: (c.free_cc / c.tickers + own).max(0),
+        window_cc: (window_budget_cc - m.pos.yes_cost_cc - m.pos.no_cost_cc).max(0),
+        short,
+        gap,
+    };
+
+    desired.takers.retain_mut(|t| {
+        if t.action == Action::Sell {
+            return true;
+        }
+        t.qty = budget.take(m, t.side, t.price_cents, t.qty);
+        t.qty > 0
+    });
+
+    let order = match short {
+        Some(s) => [s, s.other()],
+        None => Side::ALL,
+    };
+    for side in order {
+        let q = desired.quote_mut(side);
+        let QuoteIntent::Ladder(rungs) = q else { continue; };
+        // Rungs are positional: the first one that can't be funded ends the ladder.
+        let mut funded = 0;
+        for r in rungs.iter_mut() {
+            r.qty = budget.take(m, side, r.price_cents, r.qty);
+            if r.qty == 0 {
+                break;
+            }
+            funded += 1;
+        }
+        rungs.truncate(funded);
+        if rungs.is_empty() {
+            debug!(ticker, ?side, free_cc = c.free_cc, "sizing: no capital for quotes");
+            *q = QuoteIntent::Pull;
+        }
+    }
+}
+
+/// Live only: refresh `Shared.capital` every `balance_poll_ms`, or right away when
+/// something (an insufficient-balance reject) asks for it.
+pub async fn run_balance_poller(cfg: Config, client: Arc<KalshiClient>, shared: Shared) -> Result<()> {
+    loop {
+        if shared.is_shutting_down() {
+            return Ok(());
+        }
+
+        let bal = client.get_balance().await;
+        let resting = client.get_total_resting_order_value().await;
+        match (bal, resting) {
+            (Ok(b), Ok(r)) => {
+                let mut c = shared.capital();
+                let first = c.updated_at.is_none();
+                c.balance_cc = b.balance as i64 * CC_PER_CENT;
+                c.portfolio_value_cc = b.portfolio_value as i64 * CC_PER_CENT;
+                c.resting_cc = r.total_resting_order_value as i64 * CC_PER_CENT;
+                c.updated_at = Some(Instant::now());
+                if first {
+                    info!(balance_cc = c.balance_cc, resting_cc = c.resting_cc, "sizing: account balance loaded");
+                }
+            }
+            (Err(e), _) | (_, Err(e)) => warn!("balance poll failed: {e:?}"),
+        }
+
+        tokio::select! {
+            _ = sleep(Duration::from_millis(cfg.balance_poll_ms)) => {}
+            _ = shared.capital_refresh.notified() => {}
+        }
+    }
+}
@@ -1,11 +1,102 @@
-use crate::types::Side;
+use crate::types::{Side, CC_PER_CENT};
+
+/// Why the book can't be trusted until a fresh snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookFault {
+    SeqGap,
+    // A delta took a level below zero.
+    NegativeLevel,
+    // yes_bid + no_bid >= 100: those two would have matched.
+    Crossed,
+}
+
+impl BookFault {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BookFault::SeqGap => "seq_gap",
+            BookFault::NegativeLevel => "negative_level",
+            BookFault::Crossed => "crossed",
+        }
+    }
+}
+
+/// Running sums over one side's bids (a Fenwick tree), kept in step with every level change.
+///
+/// Keyed by 100 - bid: the price the *other* side pays to buy against that bid. Prefix sums
+/// therefore run from the best bid down, which is the order both depth and sweeps need.
+#[derive(Debug, Clone)]
+struct Depth {
+    // 1-based; key k lives at k + 1.
+    qty: [i64; 102],
+    // qty * key (cents): the cost of buying the other side through these bids.
+    cost: [i64; 102],
+}
+
+impl Default for Depth {
+    fn default() -> Self {
+        Self { qty: [0; 102], cost: [0; 102] }
+    }
+}
+
+impl Depth {
+    fn add(&mut self, key: u8, dq: i64) {
+        let mut i = key as usize + 1;
+        while i < 102 {
+            self.qty[i] += dq;
+            self.cost[i] += dq * key as i64;
+            i += i & i.wrapping_neg();
+        }
+    }
+
+    /// (qty, cost) over keys 0..=key.
+    fn prefix(&self, key: u8) -> (i64, i64) {
+        let (mut q, mut c) = (0, 0);
+        let mut i = key.min(100) as usize + 1;
+        while i > 0 {
+            q += self.qty[i];
+            c += self.cost[i];
+            i -= i & i.wrapping_neg();
+        }
+        (q, c)
+    }
+
+    /// The key where the running qty reaches `target`, with (qty, cost) of the keys before it.
+    /// None if there isn't that much.
+    fn reach(&self, target: i64) -> Option<(u8, i64, i64)> {
+        let (mut pos, mut q, mut c) = (0usize, 0, 0);
+        let mut step = 64;
+        while step > 0 {
+            let next = pos + step;
+            if next < 102 && q + self.qty[next] < target {
+                pos = next;
+                q += self.qty[next];
+                c += self.cost[next];
+            }
+            step /= 2;
+        }
+        (pos <= 100).then_some((pos as u8, q, c))
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Book {
     // Each index is a price in cents (0..=100), value is quantity resting.
-    pub yes_bids: [i64; 101],
-    pub no_bids: [i64; 101],
+    // Only `set_level` writes these, so `*_depth` stays in step.
+    yes_bids: [i64; 101],
+    no_bids: [i64; 101],
+    yes_depth: Depth,
+    no_depth: Depth,
     pub last_seq: i64,
+
+    // PAPER_SIM: liquidity our simulated IOCs took, already subtracted from the bids above.
+    // The next real delta (or snapshot) at a level supersedes it.
+    paper_taken_yes: [i64; 101],
+    paper_taken_no: [i64; 101],
+
+    // Resync in flight: deltas (seq, side, price, delta) wait here in arrival order until a
+    // WS snapshot (which has a seq) lands; those after its seq are then replayed on top.
+    // None = deltas apply live.
+    pub resync_buffer: Option<Vec<(i64, Side, u8, i64)>>,
 }
 
 impl Default for Book {
@@ -13,7 +104,12 @@ impl Default for Book {
         Self {
             yes_bids: [0; 101],
             no_bids: [0; 101],
+            yes_depth: Depth::default(),
+            no_depth: Depth::default(),
             last_seq: -1,
+            paper_taken_yes: [0; 101],
+            paper_taken_no: [0; 101],
+            resync_buffer: None,
         }
     }
 }
@@ -28,49 +124,161 @@ impl Book {
     }
 
     #[inline]
-    fn bids_mut(&mut self, side: Side) -> &mut [i64; 101] {
+    fn depth(&self, side: Side) -> &Depth {
         match side {
-            Side::Yes => &mut self.yes_bids,
-            Side::No => &mut self.no_bids,
+            Side::Yes => &self.yes_depth,
+            Side::No => &self.no_depth,
+        }
+    }
+
+    /// The one place a bid level changes.
+    fn set_level(&mut self, side: Side, price: u8, qty: i64) {
+        let (bids, depth) = match side {
+            Side::Yes => (&mut self.yes_bids, &mut self.yes_depth),
+            Side::No => (&mut self.no_bids, &mut self.no_depth),
+        };
+        let idx = price as usize;
+        let dq = qty - bids[idx];
+        if dq != 0 {
+            bids[idx] = qty;
+            depth.add(100 - price, dq);
+        }
+    }
+
+    #[inline]
+    fn paper_taken_mut(&mut self, side: Side) -> &mut [i64; 101] {
+        match side {
+            Side::Yes => &mut self.paper_taken_yes,
+            Side::No => &mut self.paper_taken_no,
         }
     }
 
     pub fn reset(&mut self, seq: i64, yes: &[(u8, i64)], no: &[(u8, i64)]) {
+        self.resync_buffer = None;
+        self.load_levels(yes, no);
+        self.last_seq = seq;
+    }
+
+    fn load_levels(&mut self, yes: &[(u8, i64)], no: &[(u8, i64)]) {
         self.yes_bids = [0; 101];
         self.no_bids = [0; 101];
+        self.yes_depth = Depth::default();
+        self.no_depth = Depth::default();
+        self.paper_taken_yes = [0; 101];
+        self.paper_taken_no = [0; 101];
         for &(p, q) in yes {
-            self.yes_bids[p as usize] = q.max(0);
+            self.set_level(Side::Yes, p.min(100), q.max(0));
         }
         for &(p, q) in no {
-            self.no_bids[p as usize] = q.max(0);
+            self.set_level(Side::No, p.min(100), q.max(0));
         }
-        self.last_seq = seq;
     }
 
-    pub fn apply_delta(&mut self, seq: i64, side: Side, price: u8, delta: i64) -> bool {
-        // If seq continuity breaks, caller should resync (REST orderbook or re-subscribe).
+    /// Apply one delta. Err = the book is off (seq gap, or the result makes no sense) and
+    /// the caller should resync it.
+    pub fn apply_delta(&mut self, seq: i64, side: Side, price: u8, delta: i64) -> Result<(), BookFault> {
         if self.last_seq >= 0 && seq != self.last_seq + 1 {
-            return false;
+            return Err(BookFault::SeqGap);
         }
         let idx = price as usize;
         if idx > 100 {
-            return true;
+            return Ok(());
         }
 
-        let arr = self.bids_mut(side);
-        arr[idx] = (arr[idx] + delta).max(0);
+        // The exchange never saw our simulated takes; its level is what the delta applies to.
+        let taken = std::mem::take(&mut self.paper_taken_mut(side)[idx]);
+        let level = self.bids(side)[idx] + taken + delta;
+        self.set_level(side, price, level.max(0));
         self.last_seq = seq;
-        true
+        if level < 0 {
+            return Err(BookFault::NegativeLevel);
+        }
+        self.check_crossed()
+    }
+
+    /// Best YES bid + best NO bid must stay under 100c.
+    pub fn check_crossed(&self) -> Result<(), BookFault> {
+        match (self.best_bid(Side::Yes), self.best_bid(Side::No)) {
+            (Some(y), Some(n)) if y as u16 + n as u16 >= 100 => Err(BookFault::Crossed),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn is_resyncing(&self) -> bool {
+        self.resync_buffer.is_some()
+    }
+
+    /// Start holding deltas back for a resync (no-op if one is already running).
+    pub fn begin_resync(&mut self) {
+        self.resync_buffer.get_or_insert_with(Vec::new);
+    }
+
+    pub fn buffer_delta(&mut self, seq: i64, side: Side, price: u8, delta: i64) {
+        if let Some(buf) = self.resync_buffer.as_mut() {
+            buf.push((seq, side, price, delta));
+        }
+    }
+
+    /// REST levels while a resync waits for its WS snapshot. The REST book has no seq, so
+    /// there's no telling which buffered deltas it already holds: nothing is replayed, the
+    /// book stays resyncing, and only `apply_snapshot` makes it usable again.
+    pub fn load_provisional(&mut self, yes: &[(u8, i64)], no: &[(u8, i64)]) {
+        if self.is_resyncing() {
+            self.load_levels(yes, no);
+        }
+    }
+
+    /// Take a WS snapshot at `seq`. A resync in flight ends here: buffered deltas at or before
+    /// `seq` are already in the snapshot and dropped; the rest are replayed in order.
+    pub fn apply_snapshot(&mut self, seq: i64, yes: &[(u8, i64)], no: &[(u8, i64)]) -> Result<(), BookFault> {
+        let buffered = self.resync_buffer.take().unwrap_or_default();
+        self.reset(seq, yes, no);
+        self.check_crossed()?;
+        for (d_seq, side, price, delta) in buffered.into_iter().filter(|&(d_seq, ..)| d_seq > seq) {
+            self.apply_delta(d_seq, side, price, delta)?;
+        }
+        Ok(())
+    }
+
+    /// PAPER_SIM: remove up to `qty` from a bid level (a simulated IOC hit it). Returns qty taken.
+    pub fn take_liquidity(&mut self, side: Side, price: u8, qty: u64) -> u64 {
+        let idx = price.min(100) as usize;
+        let level = self.bids(side)[idx];
+        let take = (qty as i64).min(level).max(0);
+        self.set_level(side, idx as u8, level - take);
+        self.paper_taken_mut(side)[idx] += take;
+        take as u64
+    }
+
+    /// Resting bid qty at one price: the queue ahead of a new bid placed there.
+    pub fn level_qty(&self, side: Side, price: u8) -> i64 {
+        self.bids(side)[price.min(100) as usize]
     }
 
     pub fn best_bid(&self, side: Side) -> Option<u8> {
-        let arr = self.bids(side);
-        for p in (0..=100).rev() {
-            if arr[p] > 0 {
-                return Some(p as u8);
-            }
+        self.depth(side).reach(1).map(|(key, _, _)| 100 - key)
+    }
+
+    /// Bid qty from the best bid down to `cents` below it (0 = the top level only).
+    pub fn depth_within(&self, side: Side, cents: u8) -> i64 {
+        let Some(best) = self.best_bid(side) else { return 0; };
+        self.depth(side).prefix((100 - best).saturating_add(cents)).0
+    }
+
+    /// Buying `qty` on `side` through the implied ask (sweeping the other side's bids, best
+    /// first): (worst price reached, VWAP in cc, fees excluded). None if the book is too thin.
+    pub fn buy_vwap(&self, side: Side, qty: u64) -> Option<(u8, i64)> {
+        if qty == 0 {
+            return None;
         }
-        None
+        let (worst, q_before, cost_before) = self.depth(side.other()).reach(qty as i64)?;
+        let cost_cents = cost_before + (qty as i64 - q_before) * worst as i64;
+        Some((worst, cost_cents * CC_PER_CENT / qty as i64))
+    }
+
+    /// Implied ask - best bid on one side, in cents.
+    pub fn spread(&self, side: Side) -> Option<u8> {
+        Some(self.implied_ask(side)?.saturating_sub(self.best_bid(side)?))
     }
 
     // In a binary market, buying YES at the ask is equivalent to buying NO at its bid:
@@ -87,4 +295,153 @@ impl Book {
     pub fn crosses_ask(&self, side: Side, price: u8) -> bool {
         self.implied_ask(side).map(|ask| price >= ask).unwrap_or(false)
     }
+
+    // True if a new SELL order at `price` would cross the best bid on its own side.
+    // Selling YES at p hits resting YES bids >= p.
+    pub fn crosses_bid(&self, side: Side, price: u8) -> bool {
+        self.best_bid(side).map(|bid| price <= bid).unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn snapshot_drops_buffered_deltas_it_already_holds() {
+        let mut b = Book::default();
+        b.reset(10, &[(40, 10)], &[(55, 5)]);
+
+        // Gap at 12: resync, buffering 12..=14.
+        assert_eq!(b.apply_delta(12, Side::Yes, 40, 3), Err(BookFault::SeqGap));
+        b.begin_resync();
+        b.buffer_delta(12, Side::Yes, 40, 3);
+        b.buffer_delta(13, Side::No, 55, -2);
+        b.buffer_delta(14, Side::Yes, 41, 4);
+
+        // REST levels don't end the resync or replay anything.
+        b.load_provisional(&[(40, 13)], &[(55, 3)]);
+        assert!(b.is_resyncing());
+        assert_eq!(b.level_qty(Side::Yes, 40), 13);
+
+        // The WS snapshot at seq 13 already holds 11..=13; only 14 is replayed.
+        b.apply_snapshot(13, &[(40, 13)], &[(55, 3)]).unwrap();
+        assert!(!b.is_resyncing());
+        assert_eq!(b.level_qty(Side::Yes, 40), 13);
+        assert_eq!(b.level_qty(Side::No, 55), 3);
+        assert_eq!(b.level_qty(Side::Yes, 41), 4);
+        assert_eq!(b.last_seq, 14);
+
+        // Live deltas continue from the last replayed seq.
+        assert_eq!(b.apply_delta(15, Side::Yes, 41, -4), Ok(()));
+        assert_eq!(b.best_bid(Side::Yes), Some(40));
+    }
+
+    #[test]
+    fn snapshot_with_gap_after_it_faults() {
+        let mut b = Book::default();
+        b.reset(1, &[(40, 1)], &[]);
+        b.begin_resync();
+        b.buffer_delta(7, Side::Yes, 40, 1);
+        assert_eq!(b.apply_snapshot(5, &[(40, 1)], &[]), Err(BookFault::SeqGap));
+    }
+
+    /// (qty, cost) of bids at keys 0..=key, scanning the raw array.
+    fn naive_prefix(bids: &[i64; 101], key: u8) -> (i64, i64) {
+        let (mut q, mut c) = (0, 0);
+        for k in 0..=key {
+            let n = bids[100 - k as usize];
+            q += n;
+            c += n * k as i64;
+        }
+        (q, c)
+    }
+
+    /// `Depth::reach` by walking keys best first.
+    fn naive_reach(bids: &[i64; 101], target: i64) -> Option<(u8, i64, i64)> {
+        let (mut q, mut c) = (0, 0);
+        for key in 0..=100u8 {
+            let n = bids[100 - key as usize];
+            if q + n >= target {
+                return Some((key, q, c));
+            }
+            q += n;
+            c += n * key as i64;
+        }
+        None
+    }
+
+    fn random_book(rng: &mut StdRng) -> Book {
+        let mut b = Book::default();
+        // Churn levels in place (including emptying them) so the trees see removals too.
+        for _ in 0..rng.random_range(0..200) {
+            let side = if rng.random_bool(0.5) { Side::Yes } else { Side::No };
+            let price = rng.random_range(0..=100u8);
+            let qty = if rng.random_bool(0.3) { 0 } else { rng.random_range(1..50) };
+            b.set_level(side, price, qty);
+        }
+        b
+    }
+
+    #[test]
+    fn depth_matches_a_naive_scan() {
+        let mut rng = StdRng::seed_from_u64(50);
+        for _ in 0..300 {
+            let b = random_book(&mut rng);
+            for side in Side::ALL {
+                let (bids, depth) = (b.bids(side), b.depth(side));
+                let total: i64 = bids.iter().sum();
+
+                for key in 0..=100u8 {
+                    assert_eq!(depth.prefix(key), naive_prefix(bids, key), "prefix {key}");
+                }
+                // Every target up to one past the total, so the step=64 search covers all
+                // 101 keys and the None case.
+                for target in 1..=total + 1 {
+                    assert_eq!(depth.reach(target), naive_reach(bids, target), "reach {target}");
+                }
+
+                let best = (0..=100u8).rev().find(|&p| bids[p as usize] > 0);
+                assert_eq!(b.best_bid(side), best);
+                for cents in [0, 1, 5, 100] {
+                    let want = best.map_or(0, |best| {
+                        (best.saturating_sub(cents)..=best).map(|p| bids[p as usize]).sum()
+                    });
+                    assert_eq!(b.depth_within(side, cents), want, "within {cents}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn buy_vwap_matches_sweeping_the_other_side() {
+        let mut rng = StdRng::seed_from_u64(5050);
+        for _ in 0..300 {
+            let b = random_book(&mut rng);
+            for side in Side::ALL {
+                let asks = b.bids(side.other());
+                let total: i64 = asks.iter().sum();
+                for qty in 1..=total as u64 + 1 {
+                    // Lift the other side's bids best first; buying at 100 - bid.
+                    let (mut left, mut cost, mut worst) = (qty as i64, 0, 0);
+                    for p in (0..=100u8).rev() {
+                        if left == 0 {
+                            break;
+                        }
+                        let take = asks[p as usize].min(left);
+                        if take > 0 {
+                            left -= take;
+                            cost += take * (100 - p) as i64;
+                            worst = 100 - p;
+                        }
+                    }
+                    let want = (left == 0).then(|| (worst, cost * CC_PER_CENT / qty as i64));
+                    assert_eq!(b.buy_vwap(side, qty), want, "qty {qty}");
+                }
+                assert_eq!(b.buy_vwap(side, 0), None);
+            }
+        }
+    }
 }
@@ -0,0 +1,66 @@
+use std::time::{Duration, Instant};
+
+use super::book::BookFault;
+
+/// Per-ticker market-data health, for the stale-feed breaker.
+///
+/// The WS task records what arrives; the engine trips the breaker (`check`) when the book
+/// can't be trusted and pulls the ticker's quotes. Only a fresh snapshot clears it.
+#[derive(Debug, Clone, Default)]
+pub struct FeedHealth {
+    // Last in-sequence book update (snapshot or delta).
+    pub last_book_at: Option<Instant>,
+    pub last_snapshot_at: Option<Instant>,
+    // Seq gap or a book that fails its sanity checks: wrong until the next snapshot.
+    pub fault: Option<BookFault>,
+    // A fresh snapshot (REST resync or WS resubscribe) was asked for at this time.
+    pub resync_requested_at: Option<Instant>,
+
+    // Breaker: why and since when. None = trading normally.
+    stale: Option<(&'static str, Instant)>,
+    pub trips: u64,
+}
+
+impl FeedHealth {
+    pub fn on_snapshot(&mut self, now: Instant) -> Option<(&'static str, Duration)> {
+        self.last_book_at = Some(now);
+        self.last_snapshot_at = Some(now);
+        self.fault = None;
+        self.resync_requested_at = None;
+        self.stale.take().map(|(reason, since)| (reason, now.duration_since(since)))
+    }
+
+    pub fn on_delta(&mut self, now: Instant, res: Result<(), BookFault>) {
+        match res {
+            Ok(()) => self.last_book_at = Some(now),
+            Err(f) => self.fault = Some(f),
+        }
+    }
+
+    pub fn is_stale(&self) -> bool {
+        self.stale.is_some()
+    }
+
+    /// Trip the breaker if the book is faulted, being resynced, or silent for longer than `max_silence`.
+    /// A ticker still waiting for its first snapshot has no book to quote on, so only a fault trips it.
+    /// Returns the reason when this call tripped it.
+    pub fn check(&mut self, max_silence: Duration, now: Instant) -> Option<&'static str> {
+        if self.stale.is_some() {
+            return None;
+        }
+        let reason = if let Some(f) = self.fault {
+            f.as_str()
+        } else if self.last_snapshot_at.is_none() {
+            return None;
+        } else if self.resync_requested_at.is_some() {
+            "resync"
+        } else if self.last_book_at.is_none_or(|t| now.duration_since(t) > max_silence) {
+            "silent"
+        } else {
+            return None;
+        };
+        self.stale = Some((reason, now));
+        self.trips += 1;
+        Some(reason)
+    }
+}
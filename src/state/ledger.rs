@@ -0,0 +1,92 @@
+//! Paper account: cash and what's reserved for resting buys, across all tickers.
+//!
+//! Mirrors how the exchange treats collateral:
+//! - a resting buy holds price * remaining qty plus its worst-case fee
+//! - fills move cash (cost + fee out for buys, proceeds - fee in for sells)
+//! - settlement payouts are credited when a window settles
+//!
+//! Reservations are re-derived from the order records (`sync_order`) after every paper
+//! change, so fills, cancels, amends and expiries all release the right amount.
+
+use std::collections::HashMap;
+
+use crate::state::orders::{OrderRec, OrderStatus};
+use crate::fees::FeeModel;
+use crate::types::{Action, CC_PER_CENT};
+
+#[derive(Debug, Clone, Default)]
+pub struct PaperLedger {
+    pub cash_cc: i64,
+    // client_order_id -> (ticker, reserved cc)
+    reserved: HashMap<uuid::Uuid, (String, i64)>,
+}
+
+/// Collateral a buy of `qty` at `price_cents` needs: contract cost plus the taker fee
+/// (the most it could be charged).
+pub fn buy_collateral_cc(fees: &FeeModel, price_cents: u8, qty: u64) -> i64 {
+    price_cents as i64 * CC_PER_CENT * qty as i64 + fees.taker_fee_cc(price_cents, qty)
+}
+
+impl PaperLedger {
+    pub fn new(cash_cc: i64) -> Self {
+        Self { cash_cc, reserved: HashMap::new() }
+    }
+
+    pub fn reserved_cc(&self) -> i64 {
+        self.reserved.values().map(|(_, cc)| cc).sum()
+    }
+
+    pub fn available_cc(&self) -> i64 {
+        self.cash_cc - self.reserved_cc()
+    }
+
+    /// Would an order needing `need_cc` more collateral be funded?
+    /// `held_cc` is what the order already holds (amends only pay the difference).
+    pub fn can_fund(&self, need_cc: i64, held_cc: i64) -> bool {
+        need_cc - held_cc <= self.available_cc()
+    }
+
+    pub fn held_by(&self, client_id: uuid::Uuid) -> i64 {
+        self.reserved.get(&client_id).map_or(0, |(_, cc)| *cc)
+    }
+
+    /// Re-derive one order's reservation from its record: resting buys hold collateral
+    /// for what's still unfilled; anything else holds nothing.
+    pub fn sync_order(&mut self, rec: &OrderRec, fees: &FeeModel) {
+        let live = matches!(rec.status, OrderStatus::Resting | OrderStatus::PendingAck);
+        let remaining = rec.qty.saturating_sub(rec.filled_qty);
+        if rec.action == Action::Buy && live && remaining > 0 {
+            let cc = buy_collateral_cc(fees, rec.price_cents, remaining);
+            self.reserved.insert(rec.client_order_id, (rec.ticker.clone(), cc));
+        } else {
+            self.reserved.remove(&rec.client_order_id);
+        }
+    }
+
+    /// Re-sync every reservation held for orders in `orders` (one ticker's book of orders).
+    pub fn sync_orders<'a>(&mut self, orders: impl Iterator<Item = &'a OrderRec>, fees: &FeeModel) {
+        for rec in orders {
+            if self.reserved.contains_key(&rec.client_order_id) {
+                self.sync_order(rec, fees);
+            }
+        }
+    }
+
+    /// Drop everything a ticker still holds (it's gone from Shared; its orders die with it).
+    pub fn release_ticker(&mut self, ticker: &str) {
+        self.reserved.retain(|_, (t, _)| t != ticker);
+    }
+
+    /// Cash leaves on a buy fill, comes in on a sell fill.
+    pub fn apply_fill(&mut self, action: Action, price_cents: u8, qty: u64, fee_cc: i64) {
+        let notional = price_cents as i64 * CC_PER_CENT * qty as i64;
+        match action {
+            Action::Buy => self.cash_cc -= notional + fee_cc,
+            Action::Sell => self.cash_cc += notional - fee_cc,
+        }
+    }
+
+    pub fn credit(&mut self, cc: i64) {
+        self.cash_cc += cc;
+    }
+}
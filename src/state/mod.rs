@@ -2,17 +2,40 @@ pub mod ticker;
 pub mod position;
 pub mod book;
 pub mod orders;
+pub mod ledger;
+pub mod feed;
 
 use dashmap::DashMap;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use tokio::sync::Notify;
 
+use crate::governor::LossGovernor;
+use crate::sizing::Capital;
+use ledger::PaperLedger;
 use ticker::TickerState;
 
 #[derive(Clone, Debug)]
 pub struct Shared {
     pub tickers: Arc<DashMap<String, Arc<TickerState>>>,
     pub notify: Arc<Notify>,
+
+    // Set once on SIGINT/SIGTERM. Engine stops deciding and exec stops placing.
+    pub shutdown: Arc<AtomicBool>,
+
+    // Paper mode only: simulated account cash and collateral.
+    pub paper_ledger: Arc<Mutex<PaperLedger>>,
+
+    // Daily loss / drawdown kill switch (governor.rs). Halted = engine pulls everything.
+    pub governor: Arc<Mutex<LossGovernor>>,
+
+    // Live account balance for sizing (sizing.rs); notify to force a refresh.
+    pub capital: Arc<Mutex<Capital>>,
+    pub capital_refresh: Arc<Notify>,
+
+    // Exchange open for us to trade (exchange_monitor.rs): status active and no maintenance
+    // due. Off = the engine pulls every quote and the market manager holds rotation.
+    pub trading_allowed: Arc<AtomicBool>,
 }
 
 impl Shared {
@@ -24,6 +47,12 @@ impl Shared {
         Self {
             tickers: Arc::new(map),
             notify: Arc::new(Notify::new()),
+            shutdown: Arc::new(AtomicBool::new(false)),
+            paper_ledger: Arc::new(Mutex::new(PaperLedger::default())),
+            governor: Arc::new(Mutex::new(LossGovernor::default())),
+            capital: Arc::new(Mutex::new(Capital::default())),
+            capital_refresh: Arc::new(Notify::new()),
+            trading_allowed: Arc::new(AtomicBool::new(true)),
         }
     }
 
@@ -40,5 +69,42 @@ impl Shared {
     /// Remove a ticker from Shared (engine will stop iterating it).
     pub fn remove_ticker(&self, ticker: &str) {
         self.tickers.remove(ticker);
+        // Any paper collateral its orders held goes back to the account.
+        self.ledger().release_ticker(ticker);
+    }
+
+    pub fn ledger(&self) -> std::sync::MutexGuard<'_, PaperLedger> {
+        self.paper_ledger.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn governor(&self) -> std::sync::MutexGuard<'_, LossGovernor> {
+        self.governor.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn capital(&self) -> std::sync::MutexGuard<'_, Capital> {
+        self.capital.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Flip the shutdown flag and wake the engine so it notices.
+    pub fn begin_shutdown(&self) {
+        self.shutdown.store(true, Ordering::Release);
+        self.notify.notify_one();
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutdown.load(Ordering::Acquire)
+    }
+
+    pub fn is_trading_allowed(&self) -> bool {
+        self.trading_allowed.load(Ordering::Acquire)
+    }
+
+    /// Set the trading-allowed flag and wake the engine. Returns true if it changed.
+    pub fn set_trading_allowed(&self, allowed: bool) -> bool {
+        let changed = self.trading_allowed.swap(allowed, Ordering::AcqRel) != allowed;
+        if changed {
+            self.notify.notify_one();
+        }
+        changed
     }
 }
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::time::Instant;
 
-use crate::types::{Side, Tif};
+use crate::types::{Action, Side, Tif};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OrderStatus {
@@ -16,6 +16,7 @@ pub enum OrderStatus {
 pub struct OrderRec {
     pub ticker: String,
     pub side: Side,
+    pub action: Action,
     pub price_cents: u8,
     pub qty: u64,
     pub tif: Tif,
@@ -27,6 +28,9 @@ pub struct OrderRec {
     pub status: OrderStatus,
     pub created_at: Instant,
 
+    // Unix seconds the exchange will expire this order at (None = until canceled).
+    pub expiration_ts: Option<i64>,
+
     pub filled_qty: u64,
 }
 
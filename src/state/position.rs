@@ -1,3 +1,4 @@
+use crate::fees::{FeeModel, Liquidity};
 use crate::types::{Side, CC_PER_CENT};
 
 #[derive(Debug, Clone, Default)]
@@ -6,6 +7,15 @@ pub struct Position {
     pub no_qty: i64,
     pub yes_cost_cc: i64,
     pub no_cost_cc: i64,
+
+    // PnL locked in by sells (sale proceeds minus the average cost they removed).
+    pub realized_pnl_cc: i64,
+
+    // Fee schedule for this market. Buy fees go into the leg's cost basis,
+    // so avg/pair cost are fee-inclusive; sell fees come out of proceeds.
+    pub fee_model: FeeModel,
+    // Total fees paid (buys and sells), for reporting.
+    pub fees_cc: i64,
 }
 
 impl Position {
@@ -31,6 +41,13 @@ impl Position {
         Some(self.avg_yes_cc()? + self.avg_no_cc()?)
     }
 
+    // Settlement PnL if the side we hold less of wins: min(yes, no) pays $1 each,
+    // less everything paid, plus what sells already realized.
+    pub fn worst_case_pnl_cc(&self) -> i64 {
+        let pairs = self.yes_qty.min(self.no_qty).max(0);
+        pairs * 100 * CC_PER_CENT - self.yes_cost_cc - self.no_cost_cc + self.realized_pnl_cc
+    }
+
     pub fn imbalance_ratio(&self) -> f64 {
         let diff = (self.yes_qty - self.no_qty).abs() as f64;
         let total = (self.yes_qty + self.no_qty).max(1) as f64;
@@ -41,8 +58,10 @@ impl Position {
         self.yes_qty == self.no_qty
     }
 
-    pub fn apply_fill(&mut self, side: Side, price_cents: u8, qty: i64) {
-        let add_cc = (price_cents as i64) * CC_PER_CENT * qty;
+    pub fn apply_fill(&mut self, side: Side, price_cents: u8, qty: i64, liquidity: Liquidity) {
+        let fee_cc = self.fee_model.fee_cc(liquidity, price_cents, qty.max(0) as u64);
+        self.fees_cc += fee_cc;
+        let add_cc = (price_cents as i64) * CC_PER_CENT * qty + fee_cc;
         match side {
             Side::Yes => {
                 self.yes_qty += qty;
@@ -55,9 +74,99 @@ impl Position {
         }
     }
 
-    pub fn simulate_buy(&self, side: Side, price_cents: u8, qty: i64) -> Position {
+    /// Sell `qty` of `side` at `price_cents` using average-cost reduction:
+    /// cost basis drops by avg * sold, the rest of the proceeds (net of fees) goes to realized PnL.
+    /// Sells beyond what we hold are clamped (we never go short).
+    /// Returns the realized PnL of this sell (cc).
+    pub fn apply_sell(&mut self, side: Side, price_cents: u8, qty: i64, liquidity: Liquidity) -> i64 {
+        let (held, cost) = match side {
+            Side::Yes => (&mut self.yes_qty, &mut self.yes_cost_cc),
+            Side::No => (&mut self.no_qty, &mut self.no_cost_cc),
+        };
+
+        let sold = qty.min(*held).max(0);
+        if sold == 0 {
+            return 0;
+        }
+
+        // Proportional removal keeps integer rounding from leaving cost on a flat leg.
+        let removed_cc = if sold == *held { *cost } else { *cost * sold / *held };
+        let fee_cc = self.fee_model.fee_cc(liquidity, price_cents, sold as u64);
+        let proceeds_cc = (price_cents as i64) * CC_PER_CENT * sold - fee_cc;
+
+        *held -= sold;
+        *cost -= removed_cc;
+        self.fees_cc += fee_cc;
+
+        let realized = proceeds_cc - removed_cc;
+        self.realized_pnl_cc += realized;
+        realized
+    }
+
+    pub fn simulate_buy(&self, side: Side, price_cents: u8, qty: i64, liquidity: Liquidity) -> Position {
         let mut p = self.clone();
-        p.apply_fill(side, price_cents, qty);
+        p.apply_fill(side, price_cents, qty, liquidity);
         p
     }
+
+    /// Most we can spend (cc, fees included) on `qty` more of `side` and still end at or under
+    /// `cap_cc` pair cost.
+    /// None if the other side is empty, since there's no pair cost to hold then.
+    pub fn max_buy_cost_cc(&self, side: Side, qty: i64, cap_cc: i64) -> Option<i64> {
+        let (my_qty, my_cost, other_avg) = match side {
+            Side::Yes => (self.yes_qty, self.yes_cost_cc, self.avg_no_cc()?),
+            Side::No => (self.no_qty, self.no_cost_cc, self.avg_yes_cc()?),
+        };
+        // (my_cost + spend) / (my_qty + qty) + other_avg <= cap
+        Some(((cap_cc - other_avg) * (my_qty + qty) - my_cost).max(0))
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sell_removes_average_cost_and_realizes_the_rest() {
+        let mut p = Position::default();
+        p.apply_fill(Side::Yes, 40, 10, Liquidity::Maker);
+        assert_eq!(p.yes_cost_cc, 40_000);
+
+        // Taker fee: ceil(0.07 * 4 * 0.5 * 0.5 dollars) = 7c.
+        let r = p.apply_sell(Side::Yes, 50, 4, Liquidity::Taker);
+        assert_eq!(r, 4 * 5_000 - 700 - 16_000);
+        assert_eq!((p.yes_qty, p.yes_cost_cc), (6, 24_000));
+        assert_eq!(p.fees_cc, 700);
+        assert_eq!(p.realized_pnl_cc, r);
+        assert_eq!(p.avg_yes_cc(), Some(4_000));
+    }
+
+    #[test]
+    fn sell_is_clamped_to_holdings() {
+        let mut p = Position::default();
+        p.apply_fill(Side::No, 30, 6, Liquidity::Maker);
+        let r = p.apply_sell(Side::No, 20, 10, Liquidity::Maker);
+        assert_eq!(r, 6 * 2_000 - 18_000);
+        assert_eq!((p.no_qty, p.no_cost_cc), (0, 0));
+
+        assert_eq!(p.apply_sell(Side::No, 20, 5, Liquidity::Maker), 0);
+        assert_eq!(p.apply_sell(Side::Yes, 20, 5, Liquidity::Maker), 0);
+        assert_eq!(p.realized_pnl_cc, r);
+    }
+
+    #[test]
+    fn flattening_leaves_no_cost_dust() {
+        let mut p = Position::default();
+        p.apply_fill(Side::Yes, 10, 1, Liquidity::Maker);
+        p.apply_fill(Side::Yes, 11, 2, Liquidity::Maker);
+        assert_eq!(p.yes_cost_cc, 3_200);
+
+        // 3200 / 3 doesn't divide; the last sell takes whatever cost is left.
+        assert_eq!(p.apply_sell(Side::Yes, 20, 1, Liquidity::Maker), 2_000 - 1_066);
+        assert_eq!(p.apply_sell(Side::Yes, 5, 2, Liquidity::Maker), 1_000 - 2_134);
+        assert_eq!((p.yes_qty, p.yes_cost_cc), (0, 0));
+        // Flat: realized is exactly proceeds minus everything paid.
+        assert_eq!(p.realized_pnl_cc, 3_000 - 3_200);
+    }
 }
@@ -1,7 +1,10 @@
-use crate::state::{book::Book, orders::Orders, position::Position};
-use crate::types::{RestingHint, Side};
+use crate::state::{book::Book, feed::FeedHealth, orders::{OrderStatus, Orders}, position::Position};
+use crate::types::{PairLockIn, PauseSource, Side, TradingPause, WorkingOrder};
+use crate::engine::risk::RiskState;
+use crate::exec::telemetry::{CmdKind, ExecStats};
 use crate::state::Shared;
 
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
 use std::time::Instant;
 use tokio::sync::RwLock;
@@ -20,17 +23,38 @@ pub struct Market {
     pub close_ts: Option<i64>,
 
     pub book: Book,
+    // Book freshness; a tripped breaker pulls quotes until the next snapshot (state::feed).
+    pub feed: FeedHealth,
 
     pub pos: Position,
     pub orders: Orders,
 
-    pub resting_yes: Option<RestingHint>,
-    pub resting_no: Option<RestingHint>,
+    // Our resting buy quotes per side (one per ladder rung).
+    pub working_yes: Vec<WorkingOrder>,
+    pub working_no: Vec<WorkingOrder>,
 
     // Cooldowns for takers so we don’t spam.
     pub last_taker_yes: Option<Instant>,
     pub last_taker_no: Option<Instant>,
 
+    // Early pair exit in progress (engine::lockin). While set, no new buys.
+    pub lockin: Option<PairLockIn>,
+
+    // Trading paused for this ticker: the engine is suspended until it clears.
+    pub pause: Option<TradingPause>,
+
+    // Exec latency/outcome samples for this ticker (exec::telemetry).
+    pub exec_stats: ExecStats,
+
+    // Pre-trade risk gate state: order rate, ids sent, rejection counts (engine::risk).
+    pub risk: RiskState,
+
+    // After an insufficient-balance reject: no new buys until then (sizing.rs).
+    pub buy_backoff_until: Option<Instant>,
+
+    // PAPER_SIM: qty the simulated exchange matched per order but hasn't reported yet.
+    pub paper_fills_in_flight: HashMap<uuid::Uuid, u64>,
+
     pub mode: Mode,
 }
 
@@ -40,28 +64,174 @@ impl Market {
             open_ts: None,
             close_ts: None,
             book: Book::default(),
+            feed: FeedHealth::default(),
             pos: Position::default(),
             orders: Orders::default(),
-            resting_yes: None,
-            resting_no: None,
+            working_yes: Vec::new(),
+            working_no: Vec::new(),
             last_taker_yes: None,
             last_taker_no: None,
+            lockin: None,
+            pause: None,
+            exec_stats: ExecStats::default(),
+            risk: RiskState::default(),
+            buy_backoff_until: None,
+            paper_fills_in_flight: HashMap::new(),
             mode: Mode::Accumulate,
         }
     }
 
-    pub fn resting_hint_mut(&mut self, side: Side) -> &mut Option<RestingHint> {
+    pub fn working_mut(&mut self, side: Side) -> &mut Vec<WorkingOrder> {
         match side {
-            Side::Yes => &mut self.resting_yes,
-            Side::No => &mut self.resting_no,
+            Side::Yes => &mut self.working_yes,
+            Side::No => &mut self.working_no,
         }
     }
 
-    pub fn resting_hint(&self, side: Side) -> &Option<RestingHint> {
+    pub fn working(&self, side: Side) -> &[WorkingOrder] {
         match side {
-            Side::Yes => &self.resting_yes,
-            Side::No => &self.resting_no,
+            Side::Yes => &self.working_yes,
+            Side::No => &self.working_no,
+        }
+    }
+
+    pub fn working_by_client_mut(&mut self, side: Side, client_id: uuid::Uuid) -> Option<&mut WorkingOrder> {
+        self.working_mut(side).iter_mut().find(|w| w.client_order_id == client_id)
+    }
+
+    /// Stop tracking the working order with this client id (either side).
+    pub fn forget_working_client(&mut self, client_id: uuid::Uuid) {
+        for side in Side::ALL {
+            self.working_mut(side).retain(|w| w.client_order_id != client_id);
+        }
+    }
+
+    /// Stop tracking the working order with this exchange id (either side).
+    pub fn forget_working_order(&mut self, order_id: &str) {
+        for side in Side::ALL {
+            self.working_mut(side).retain(|w| w.order_id.as_deref() != Some(order_id));
+        }
+    }
+
+    /// Call before recording a fill: if it's the order's first, sample decision -> first fill.
+    /// (An amended order's record starts at the amend decision.)
+    pub fn note_first_fill(&mut self, client_id: uuid::Uuid, now: Instant) {
+        if let Some(rec) = self.orders.by_client.get(&client_id)
+            && rec.filled_qty == 0
+        {
+            let latency = now.duration_since(rec.created_at);
+            self.exec_stats.record_first_fill(CmdKind::Place, latency);
+        }
+    }
+
+    pub fn forget_all_working(&mut self) {
+        for side in Side::ALL {
+            self.working_mut(side).clear();
+        }
+    }
+
+    pub fn has_working(&self) -> bool {
+        Side::ALL.iter().any(|&side| !self.working(side).is_empty())
+    }
+
+    /// Mark the ticker paused. If the exchange cancels resting orders on pause
+    /// (`quotes_canceled`), our working orders are gone too.
+    /// Returns true if this started a new pause.
+    pub fn enter_pause(
+        &mut self,
+        source: PauseSource,
+        now: Instant,
+        retry_at: Option<Instant>,
+        quotes_canceled: bool,
+    ) -> bool {
+        if quotes_canceled {
+            for side in Side::ALL {
+                for w in std::mem::take(self.working_mut(side)) {
+                    self.orders.set_status_by_client(w.client_order_id, OrderStatus::Canceled);
+                }
+            }
+        }
+
+        match self.pause.as_mut() {
+            // An exchange-wide pause outranks the retry-based ones.
+            Some(p) => {
+                if source == PauseSource::Exchange {
+                    p.source = source;
+                    p.retry_at = None;
+                } else if p.source != PauseSource::Exchange {
+                    p.retry_at = retry_at;
+                }
+                false
+            }
+            None => {
+                self.pause = Some(TradingPause { source, since: now, retry_at });
+                true
+            }
+        }
+    }
+
+    /// Is trading paused right now? Retry-based pauses lapse on their own.
+    pub fn is_paused(&mut self, now: Instant) -> bool {
+        let lapsed = self.pause
+            .as_ref()
+            .and_then(|p| p.retry_at)
+            .is_some_and(|t| now >= t);
+        if lapsed {
+            self.pause = None;
+        }
+        self.pause.is_some()
+    }
+
+    /// The exchange accepted an amend: the old order is gone and `new_client` is live
+    /// as `order_id`. The working order follows it (fresh age, fresh queue spot).
+    pub fn apply_amend_ack(
+        &mut self,
+        side: Side,
+        old_client: uuid::Uuid,
+        new_client: uuid::Uuid,
+        order_id: &str,
+        status: OrderStatus,
+        now: Instant,
+    ) {
+        self.orders.set_status_by_client(old_client, OrderStatus::Canceled);
+        self.orders.link_order_id(new_client, order_id);
+        self.orders.set_status_by_client(new_client, status);
+
+        let Some(price) = self.orders.by_client.get(&new_client).map(|r| r.price_cents) else { return; };
+        // A new price means the back of the new level's queue.
+        let queue_ahead = self.book.level_qty(side, price);
+
+        if status != OrderStatus::Resting {
+            self.forget_working_client(old_client);
+            return;
+        }
+        let Some(w) = self.working_by_client_mut(side, old_client) else { return; };
+        w.price_cents = price;
+        w.created_at = now;
+        w.cancel_requested_at = None;
+        w.client_order_id = new_client;
+        w.order_id = Some(order_id.to_string());
+        w.queue_ahead = queue_ahead;
+        w.queue_behind = 0;
+    }
+
+    /// Retire every order whose `expiration_ts` has passed (the exchange drops these
+    /// without telling us) and stop tracking any working order pointing at one.
+    /// Returns how many orders expired.
+    pub fn expire_orders(&mut self, now_s: i64) -> usize {
+        let mut expired = Vec::new();
+        for rec in self.orders.by_client.values_mut() {
+            let live = matches!(rec.status, OrderStatus::Resting | OrderStatus::PendingAck);
+            if live && rec.expiration_ts.is_some_and(|t| t <= now_s) {
+                rec.status = OrderStatus::Canceled;
+                expired.push(rec.client_order_id);
+            }
+        }
+
+        for side in Side::ALL {
+            self.working_mut(side).retain(|w| !expired.contains(&w.client_order_id));
         }
+        expired.len()
     }
 
     #[inline]
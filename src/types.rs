@@ -45,6 +45,39 @@ impl fmt::Display for Side {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Buy,
+    Sell,
+}
+
+impl Action {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Action::Buy => "buy",
+            Action::Sell => "sell",
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "buy" => Ok(Action::Buy),
+            "sell" => Ok(Action::Sell),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Tif {
     Ioc,
@@ -60,36 +93,67 @@ impl Tif {
     }
 }
 
+/// A new order, as the engine staged it and exec sends it.
+#[derive(Debug, Clone)]
+pub struct PlaceParams {
+    pub ticker: String,
+    pub side: Side,
+    // Buy adds to `side`, Sell unwinds it (price is still the `side` price).
+    pub action: Action,
+    pub price_cents: u8,
+    pub qty: u64,
+    pub tif: Tif,
+    pub post_only: bool,
+    // Only meaningful for sells: never let the order flip us short.
+    pub reduce_only: bool,
+    // Unix seconds; the exchange cancels the order at this time (GTC quotes only).
+    pub expiration_ts: Option<i64>,
+    // Total spend limit for buys, in cc; the exchange won't fill past it.
+    pub buy_max_cost_cc: Option<i64>,
+    pub client_order_id: uuid::Uuid,
+    // When the engine decided this (start of the exec latency measurement).
+    pub decided_at: std::time::Instant,
+}
+
 #[derive(Debug, Clone)]
 pub enum ExecCommand {
-    PlaceOrder {
+    Place(PlaceParams),
+    Cancel {
         ticker: String,
-        side: Side,
-        price_cents: u8,
-        qty: u64,
-        tif: Tif,
-        post_only: bool,
-        client_order_id: uuid::Uuid,
+        order_id: String,
+        decided_at: std::time::Instant,
     },
-    CancelOrder {
+    /// Move a resting order to a new price/size in one request.
+    /// The exchange replaces it, so the result is tracked under `updated_client_order_id`.
+    Amend {
         ticker: String,
+        side: Side,
+        action: Action,
         order_id: String,
+        client_order_id: uuid::Uuid,
+        updated_client_order_id: uuid::Uuid,
+        price_cents: u8,
+        // Total contracts including what already filled (exchange amend semantics).
+        count: u64,
+        decided_at: std::time::Instant,
     },
 }
 
-/// Tracks a resting order we believe is live (or pending ack).
+/// Tracks a resting buy quote we believe is live (or pending ack).
+/// Each side keeps one per ladder rung (rung 0 is the top quote).
 ///
 /// We keep this so the engine can:
 /// - avoid placing duplicates
 /// - decide when to cancel/replace
 /// - avoid churn (min_resting_life_ms)
 #[derive(Debug, Clone)]
-pub struct RestingHint {
+pub struct WorkingOrder {
     pub side: Side,
+    pub rung: usize,
     pub price_cents: u8,
     pub created_at: Instant,
 
-    // If we’ve sent a cancel, we set this to avoid re-sending cancel every tick.
+    // If we’ve sent a cancel (or amend), we set this to avoid re-sending every tick.
     pub cancel_requested_at: Option<Instant>,
 
     pub client_order_id: uuid::Uuid,
@@ -97,8 +161,55 @@ pub struct RestingHint {
     // Filled in by executor once HTTP create_order returns the exchange order id.
     pub order_id: Option<String>,
 
-    // PAPER_SIM: qty that was already resting at this price when we joined.
+    // PAPER_SIM: our place in the FIFO queue at this price (exec::paper).
+    // The level's book qty is queue_ahead + queue_behind; we aren't in the real book.
     pub queue_ahead: i64,
+    pub queue_behind: i64,
+}
+
+/// Where we learned trading is paused for a ticker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseSource {
+    // get_exchange_status says trading is off; only the monitor clears it.
+    Exchange,
+    // An order request failed with a pause/halt error; retried after `pause_retry_ms`.
+    OrderError,
+    // A WS error mentioned a pause; retried after `pause_retry_ms`.
+    Ws,
+}
+
+#[derive(Debug, Clone)]
+pub struct TradingPause {
+    pub source: PauseSource,
+    pub since: Instant,
+    // None = wait for the exchange monitor to lift it.
+    pub retry_at: Option<Instant>,
+}
+
+/// An in-progress early pair exit: sell `pairs` matched YES/NO pairs at the bids
+/// because the bids already pay more than our pair cost (plus fees).
+///
+/// Legs go out as IOC sells one at a time; `orders` lets us count what actually sold
+/// on each side, so buys that land meanwhile don't confuse the bookkeeping.
+#[derive(Debug, Clone)]
+pub struct PairLockIn {
+    pub started_at: Instant,
+    pub pairs: i64,
+
+    // Pair cost (cc) when we committed; the second leg must still clear it.
+    pub entry_pair_cc: i64,
+
+    // Every IOC sell we've sent for this lock-in.
+    pub orders: Vec<uuid::Uuid>,
+
+    // Side and limit price (cents) of the first leg we sent.
+    pub first_leg: Option<(Side, u8)>,
+
+    pub last_leg_yes: Option<Instant>,
+    pub last_leg_no: Option<Instant>,
+
+    // Set while one leg has sold more than the other (we're carrying naked inventory).
+    pub leg_risk_since: Option<Instant>,
 }
 
 /// Commands sent from MarketManager -> WS task
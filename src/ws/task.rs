@@ -1,10 +1,11 @@
 use anyhow::Result;
-use tokio::time::{sleep, Duration};
+use tokio::time::{sleep, Duration, MissedTickBehavior};
 use tracing::{info, warn};
 use uuid::Uuid;
 
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::Instant;
 
 use tokio::sync::mpsc;
 
@@ -15,17 +16,25 @@ use kalshi_rs::websocket::models::{
 };
 
 use crate::config::Config;
+use crate::fees::Liquidity;
+use crate::shadow::ShadowProfile;
+use crate::state::book::BookFault;
 use crate::state::Shared;
-use crate::types::{Side, WsMarketCommand};
+use crate::types::{Action, Side, WsMarketCommand};
 
 const WS_CHANNELS: [&str; 3] = ["orderbook_delta", "trade", "fill"];
+// How often to look for tickers whose stale-feed breaker needs a fresh snapshot.
+const FEED_CHECK_EVERY: Duration = Duration::from_secs(1);
+// Between REST orderbook attempts while a ticker resyncs.
+const RESYNC_RETRY: Duration = Duration::from_millis(500);
 
 pub async fn run_ws(
     ws: KalshiWebsocketClient,
-    _http: Arc<KalshiClient>,
+    http: Arc<KalshiClient>,
     cfg: Config,
     shared: Shared,
     initial_tickers: Vec<String>,
+    shadows: Vec<ShadowProfile>,
     mut ctl_rx: mpsc::Receiver<WsMarketCommand>,
 ) -> Result<()> {
     // Track our current subscribed markets locally so reconnects resubscribe correctly.
@@ -34,6 +43,11 @@ pub async fn run_ws(
     // channel -> sid
     let mut sids: HashMap<String, u64> = HashMap::new();
 
+    // Main profile first, then shadows: every book resync covers all of them.
+    let profiles: Vec<(Config, Shared)> = std::iter::once((cfg.clone(), shared.clone()))
+        .chain(shadows.iter().map(|s| (s.cfg.clone(), s.shared.clone())))
+        .collect();
+
     // Commands that arrive before we have sids can be queued.
     let mut pending: Vec<WsMarketCommand> = Vec::new();
 
@@ -64,6 +78,9 @@ pub async fn run_ws(
 
         info!("ws connected+subscribed to {} tickers", markets.len());
 
+        let mut feed_check = tokio::time::interval(FEED_CHECK_EVERY);
+        feed_check.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
         // Inner loop: handle WS messages and control commands concurrently.
         loop {
             tokio::select! {
@@ -93,30 +110,63 @@ pub async fn run_ws(
                             handle_ok(ok);
                         }
                         KalshiSocketMessage::ErrorResponse(err) => {
-                            handle_err(err);
+                            handle_err(&cfg, &shared, &shadows, err).await;
                         }
 
                         KalshiSocketMessage::OrderbookSnapshot(snap) => {
-                            handle_snapshot(&shared, snap).await?;
+                            let mut fault = None;
+                            for s in &shadows {
+                                fault = fault.or(handle_snapshot(&s.cfg, &s.shared, &snap).await?);
+                            }
+                            fault = fault.or(handle_snapshot(&cfg, &shared, &snap).await?);
+                            if let Some(f) = fault {
+                                start_resync(&http, &ws, &sids, &profiles, &snap.msg.market_ticker, f).await;
+                            }
                         }
                         KalshiSocketMessage::OrderbookDelta(delta) => {
-                            let ok = handle_delta(&cfg, &shared, delta).await?;
-                            if !ok {
-                                warn!("orderbook seq gap detected; reconnecting");
-                                break;
+                            // Shadows keep their own books off the same feed.
+                            let mut fault = None;
+                            for s in &shadows {
+                                fault = fault.or(handle_delta(&s.cfg, &s.shared, &delta).await?);
+                            }
+                            fault = fault.or(handle_delta(&cfg, &shared, &delta).await?);
+                            // Only this ticker resyncs; every other stream carries on.
+                            if let Some(f) = fault {
+                                start_resync(&http, &ws, &sids, &profiles, &delta.msg.market_ticker, f).await;
                             }
                         }
                         KalshiSocketMessage::TradeUpdate(tu) => {
                             // println!("TradeUpdate: {:#?}", tu);
-                            handle_trade(&cfg, &shared, tu).await?;
+                            for s in &shadows {
+                                handle_trade(&s.cfg, &s.shared, &tu).await?;
+                            }
+                            handle_trade(&cfg, &shared, &tu).await?;
                         }
                         KalshiSocketMessage::UserFill(uf) => {
+                            // Real fills are the main profile's only.
                             handle_fill(&shared, uf).await?;
                         }
                         _ => {}
                     }
                 }
 
+                _ = feed_check.tick() => {
+                    if !has_all_sids(&sids) {
+                        continue;
+                    }
+                    let (due, stale) = take_resync_due(&cfg, &shared, &markets).await;
+                    if stale > 0 && stale == markets.len() {
+                        warn!(tickers = stale, "feed stale on every ticker; reconnecting");
+                        break;
+                    }
+                    if !due.is_empty() {
+                        info!(tickers = ?due, "feed stale; resubscribing for fresh snapshots");
+                        if let Err(e) = resubscribe_books(&ws, &sids, &due).await {
+                            warn!("ws book resubscribe failed: {e:?}");
+                        }
+                    }
+                }
+
                 cmd = ctl_rx.recv() => {
                     let Some(cmd) = cmd else { return Ok(()); };
 
@@ -153,8 +203,15 @@ fn handle_ok(ok: OkResponse) {
     info!("ok response id={} sid={} markets={:?}", ok.id, ok.sid, ok.msg.market_tickers);
 }
 
-fn handle_err(err: ErrorResponse) {
+async fn handle_err(cfg: &Config, shared: &Shared, shadows: &[ShadowProfile], err: ErrorResponse) {
     warn!("ws error id={} code={} msg={}", err.id, err.msg.code, err.msg.msg);
+    if crate::exec::http::is_pause_message(&err.msg.msg) {
+        let retry_at = std::time::Instant::now() + Duration::from_millis(cfg.pause_retry_ms);
+        crate::exchange_monitor::pause_all(cfg, shared, crate::types::PauseSource::Ws, Some(retry_at)).await;
+        for s in shadows {
+            crate::exchange_monitor::pause_all(&s.cfg, &s.shared, crate::types::PauseSource::Ws, Some(retry_at)).await;
+        }
+    }
 }
 
 fn has_all_sids(sids: &HashMap<String,u64>) -> bool {
@@ -198,45 +255,160 @@ async fn apply_update_subscription(
     Ok(())
 }
 
+/// Tickers whose breaker is tripped and due a snapshot request (marked requested), and how
+/// many are stale in all. This also re-asks for a resync whose snapshot never came.
+async fn take_resync_due(cfg: &Config, shared: &Shared, markets: &HashSet<String>) -> (Vec<String>, usize) {
+    let now = Instant::now();
+    let retry = Duration::from_millis(cfg.feed_stale_ms);
+    let mut due = Vec::new();
+    let mut stale = 0;
+    for ticker in markets {
+        let Some(ts) = shared.tickers.get(ticker).map(|r| r.value().clone()) else { continue; };
+        let mut g = ts.mkt.write().await;
+        if !g.feed.is_stale() {
+            continue;
+        }
+        stale += 1;
+        if g.feed.resync_requested_at.is_some_and(|t| now.duration_since(t) < retry) {
+            continue;
+        }
+        g.feed.resync_requested_at = Some(now);
+        due.push(ticker.clone());
+    }
+    (due, stale)
+}
+
+/// Drop and re-add tickers on the orderbook channel; the exchange answers with a snapshot.
+async fn resubscribe_books(ws: &KalshiWebsocketClient, sids: &HashMap<String, u64>, tickers: &[String]) -> Result<()> {
+    let Some(&sid) = sids.get("orderbook_delta") else { return Ok(()); };
+    let refs: Vec<&str> = tickers.iter().map(|s| s.as_str()).collect();
+    ws.del_markets(vec![sid], refs.clone()).await?;
+    ws.add_markets(vec![sid], refs).await?;
+    Ok(())
+}
+
+/// Resync one ticker's book, in every profile: buffer its deltas, load the REST book as a
+/// stopgap, and resubscribe for a WS snapshot. That snapshot (it has a seq) ends the resync.
+async fn start_resync(
+    http: &Arc<KalshiClient>,
+    ws: &KalshiWebsocketClient,
+    sids: &HashMap<String, u64>,
+    profiles: &[(Config, Shared)],
+    ticker: &str,
+    fault: BookFault,
+) {
+    let now = Instant::now();
+    let mut already = false;
+    for (_, shared) in profiles {
+        let Some(ts) = shared.tickers.get(ticker).map(|r| r.value().clone()) else { continue; };
+        let mut g = ts.mkt.write().await;
+        already |= g.book.is_resyncing();
+        g.book.begin_resync();
+        g.feed.resync_requested_at = Some(now);
+    }
+    if already {
+        return;
+    }
+
+    warn!(ticker, reason = fault.as_str(), "orderbook fault; resyncing ticker");
+    tokio::spawn(load_rest_book(http.clone(), profiles.to_vec(), ticker.to_string()));
+    // If this fails, the feed check asks again once `feed_stale_ms` has passed.
+    if let Err(e) = resubscribe_books(ws, sids, &[ticker.to_string()]).await {
+        warn!(ticker, "ws book resubscribe for resync failed: {e:?}");
+    }
+}
+
+/// Fetch the REST orderbook into each profile's resyncing book (until the WS snapshot wins
+/// the race, or the ticker is gone).
+async fn load_rest_book(http: Arc<KalshiClient>, profiles: Vec<(Config, Shared)>, ticker: String) {
+    loop {
+        let mut waiting = false;
+        for (_, shared) in &profiles {
+            if let Some(ts) = shared.tickers.get(&ticker).map(|r| r.value().clone()) {
+                waiting |= ts.mkt.read().await.book.is_resyncing();
+            }
+        }
+        if !waiting {
+            return;
+        }
+
+        let book = match http.get_market_orderbook(&ticker, None).await {
+            Ok(r) => r.orderbook,
+            Err(e) => {
+                warn!(ticker = %ticker, "orderbook resync fetch failed: {e:?}");
+                sleep(RESYNC_RETRY).await;
+                continue;
+            }
+        };
+        let levels = |v: Option<Vec<(u64, u64)>>| -> Vec<(u8, i64)> {
+            v.unwrap_or_default().into_iter().filter(|&(p, _)| p <= 100).map(|(p, q)| (p as u8, q as i64)).collect()
+        };
+        let (yes, no) = (levels(book.yes), levels(book.no));
+
+        for (_, shared) in &profiles {
+            let Some(ts) = shared.tickers.get(&ticker).map(|r| r.value().clone()) else { continue; };
+            // No-op if the WS snapshot already landed; it's newer than ours.
+            ts.mkt.write().await.book.load_provisional(&yes, &no);
+        }
+        info!(ticker = %ticker, "orderbook: REST book loaded; waiting for WS snapshot");
+        return;
+    }
+}
+
 // --- your existing handlers below (unchanged except signature tweaks if needed) ---
 
-async fn handle_snapshot(shared: &Shared, snap: OrderbookSnapshot) -> Result<()> {
+async fn handle_snapshot(cfg: &Config, shared: &Shared, snap: &OrderbookSnapshot) -> Result<Option<BookFault>> {
     let seq = snap.seq;
-    let m = snap.msg;
+    let m = &snap.msg;
     let ticker = m.market_ticker.clone();
-    let yes = m.yes.unwrap_or_default();
-    let no = m.no.unwrap_or_default();
+    let yes = m.yes.clone().unwrap_or_default();
+    let no = m.no.clone().unwrap_or_default();
 
     let Some(ts) = shared.tickers.get(&ticker) else {
-        return Ok(());
+        return Ok(None);
     };
     let mut g = ts.mkt.write().await;
-    g.book.reset(seq, &yes, &no);
+    // Ends a resync too: buffered deltas past `seq` are replayed on top.
+    if let Err(f) = g.book.apply_snapshot(seq, &yes, &no) {
+        g.feed.fault = Some(f);
+        ts.touch(shared);
+        return Ok(Some(f));
+    }
+    if let Some((reason, stale_for)) = g.feed.on_snapshot(Instant::now()) {
+        info!(ticker = %ticker, reason, stale_ms = stale_for.as_millis() as u64, "feed: fresh snapshot; quoting resumes");
+    }
+    if cfg.exec_mode.is_paper() {
+        crate::exec::paper::paper_resync_queues(&mut g, cfg.paper_queue_cancel_rule);
+    }
 
     ts.touch(&shared);
-    Ok(())
+    Ok(None)
 }
 
-async fn handle_delta(cfg: &Config, shared: &Shared, delta: OrderbookDelta) -> Result<bool> {
+/// Some(fault) = this ticker's book needs a resync.
+async fn handle_delta(cfg: &Config, shared: &Shared, delta: &OrderbookDelta) -> Result<Option<BookFault>> {
     let seq = delta.seq;
-    let m = delta.msg;
+    let m = &delta.msg;
     let ticker = m.market_ticker.clone();
-    let Some(side) = m.side.parse::<Side>().ok() else { return Ok(true); };
+    let Some(side) = m.side.parse::<Side>().ok() else { return Ok(None); };
 
     let ts = shared.ensure_ticker(&ticker);
     let mut g = ts.mkt.write().await;
-    let ok = g.book.apply_delta(seq, side, m.price, m.delta);
-    if ok {
-        if cfg.exec_mode.is_paper() {
-            crate::exec::paper::paper_on_delta_queue(&mut g, side, m.price, m.delta);
-        }
+    if g.book.is_resyncing() {
+        g.book.buffer_delta(seq, side, m.price, m.delta);
+        return Ok(None);
+    }
+    let res = g.book.apply_delta(seq, side, m.price, m.delta);
+    g.feed.on_delta(Instant::now(), res);
+    if res.is_ok() && cfg.exec_mode.is_paper() {
+        crate::exec::paper::paper_on_delta_queue(&mut g, side, m.price, cfg.paper_queue_cancel_rule);
     }
     ts.touch(&shared);
-    Ok(ok)
+    Ok(res.err())
 }
 
-async fn handle_trade(cfg: &Config, shared: &Shared, tu: TradeUpdate) -> Result<()> {
-    let m = tu.msg;
+async fn handle_trade(cfg: &Config, shared: &Shared, tu: &TradeUpdate) -> Result<()> {
+    let m = &tu.msg;
     let ticker = m.market_ticker.clone();
     let Some(taker_side) = m.taker_side.parse::<Side>().ok() else { return Ok(()); };
 
@@ -244,7 +416,8 @@ async fn handle_trade(cfg: &Config, shared: &Shared, tu: TradeUpdate) -> Result<
     let mut g = ts.mkt.write().await;
 
     if cfg.exec_mode.is_paper() {
-        crate::exec::paper::paper_on_trade_fill(&ticker, &mut g, taker_side, m.yes_price, m.no_price, m.count);
+        let fills = crate::exec::paper::paper_on_trade_fill(&ticker, &mut g, taker_side, m.yes_price, m.no_price, m.count);
+        crate::exec::paper::deliver_fills(shared, &ticker, &mut g, fills, cfg.paper_fill_delay_ms);
     }
 
     ts.touch(&shared);
@@ -255,7 +428,14 @@ async fn handle_fill(shared: &Shared, uf: UserFill) -> Result<()> {
     let m = uf.msg;
     let ticker = m.market_ticker.clone();
 
-    let Some(purchased) = m.purchased_side.parse::<Side>().ok() else { return Ok(()); };
+    // Buys: purchased_side is what we now hold more of.
+    // Sells: `side` is the leg we unwound (purchased_side is the other one).
+    let action = m.action.parse::<Action>().unwrap_or(Action::Buy);
+    let side_str = match action {
+        Action::Buy => &m.purchased_side,
+        Action::Sell => &m.side,
+    };
+    let Some(purchased) = side_str.parse::<Side>().ok() else { return Ok(()); };
     
     let fill_qty = m.count.max(0) as i64;
     if fill_qty == 0 { return Ok(()); }
@@ -265,35 +445,39 @@ async fn handle_fill(shared: &Shared, uf: UserFill) -> Result<()> {
         Side::No => 100u8.saturating_sub(m.yes_price),
     };
 
+    let liquidity = Liquidity::from_is_taker(m.is_taker);
+
     if let Some(ts) = shared.tickers.get(&ticker) {
         let mut g = ts.mkt.write().await;
 
-        // Update position.
-        g.pos.apply_fill(purchased, price, fill_qty);
+        // Update position (fees per the market's schedule).
+        match action {
+            Action::Buy => g.pos.apply_fill(purchased, price, fill_qty, liquidity),
+            Action::Sell => {
+                let realized = g.pos.apply_sell(purchased, price, fill_qty, liquidity);
+                info!(ticker = %ticker, side = ?purchased, price, fill_qty, realized_cc = realized, "sell filled");
+            }
+        }
         // crate::report::log_position(&ticker, &g.pos);
         if let Ok(client_id) = Uuid::parse_str(&m.client_order_id) {
             // Make sure order_id mapping exists even if Rest ack is late
             g.orders.link_order_id_if_missing(client_id, &m.order_id);
+            g.note_first_fill(client_id, std::time::Instant::now());
 
             let fully_filled = g.orders.record_fill_by_order(&m.order_id, fill_qty as u64);
             
             if matches!(fully_filled, Some(true)) {
-                if let Some(h) = g.resting_hint(purchased).as_ref() {
-                    if h.order_id.as_deref() == Some(&m.order_id.as_str()) {
-                        *g.resting_hint_mut(purchased) = None;
-                    }
-                }
+                g.forget_working_order(&m.order_id);
             }
         } else {
             // Fallback: apply by order_id (works only if by_order mapping exists)
+            if let Some(client_id) = g.orders.by_order.get(&m.order_id).copied() {
+                g.note_first_fill(client_id, std::time::Instant::now());
+            }
             let fully_filled = g.orders.record_fill_by_order(&m.order_id, fill_qty as u64);
             
             if matches!(fully_filled, Some(true)) {
-                if let Some(h) = g.resting_hint(purchased).as_ref() {
-                    if h.order_id.as_deref() == Some(m.order_id.as_str()) {
-                        *g.resting_hint_mut(purchased) = None;
-                    }
-                }
+                g.forget_working_order(&m.order_id);
             }            
         }
 